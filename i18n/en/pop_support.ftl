@@ -1,15 +1,30 @@
+battery = Battery
+battery-health = {$health}% of design capacity
 create-logs = Create Log Archives for Support
 create-logs-button = {log-dialog}
 dialog-close = Close
 dialog-show-in-folder = Show in Folder
 documentation = Documentation
 documentation-button = Browse
+firmware = Firmware
+graphics = Graphics
 log-dialog = Create Log Files
 log-dialog-creating = Creating Files...
 log-dialog-finished = A log archive ({$archive}) was created.
+log-dialog-finished-encrypted = A log archive ({$archive}) was created. It is encrypted so that only {$recipients} can read it.
+log-window-boot = Current boot
+log-window-boots = Last 3 boots
+log-window-week = Past week
+log-window-yesterday = Since yesterday
+memory = Memory
 model-and-version = Model and Version
 os-version = Operating System and Version
+processor = Processor
+processor-cores = {$model} ({$cores} cores, {$threads} threads)
 serial-number = Serial Number
+serial-number-button = Show
+serial-number-copy = Copy
+storage = Storage
 support-community = Community Support in Pop!_OS Chat
 support-community-button = Join
 support-professional = Professional Support
@@ -14,7 +14,8 @@ mod vendor;
 
 pub use self::vendor::Vendor;
 
-use self::support_info::SupportInfo;
+use self::logs::{LogArchive, TimeWindow};
+use self::support_info::{Hardware, SupportInfo};
 use self::widgets::*;
 use anyhow::Context;
 use gtk::prelude::*;
@@ -34,15 +35,20 @@ pub fn localize() {
 
 #[derive(Debug, relm_derive::Msg)]
 pub enum SupportEvent {
-    UpdateInfo(SupportInfo),
+    UpdateInfo(Box<SupportInfo>),
     BrowseDocumentation,
     CommunitySupport,
+    CopySerial,
     CreateLogFiles,
     CreateSupportTicket,
+    ReadSerial,
+    SerialRead(anyhow::Result<String>),
 }
 
 pub struct SupportModel {
     vendor: Option<Vendor>,
+    serial_number: Option<String>,
+    stream: relm::StreamHandle<SupportEvent>,
     window: gtk::Window,
     log_dialog: Option<relm::Component<LogDialog>>,
 }
@@ -63,8 +69,14 @@ impl Widget for SupportPanel {
         cascade! {
             gtk::SizeGroup::new(gtk::SizeGroupMode::Both);
             ..add_widget(&self.widgets.model_info);
-            ..add_widget(&self.widgets.serial_info);
+            ..add_widget(&self.widgets.serial_box);
             ..add_widget(&self.widgets.os_info);
+            ..add_widget(&self.widgets.processor_info);
+            ..add_widget(&self.widgets.memory_info);
+            ..add_widget(&self.widgets.graphics_info);
+            ..add_widget(&self.widgets.storage_info);
+            ..add_widget(&self.widgets.firmware_info);
+            ..add_widget(&self.widgets.battery_info);
             ..add_widget(&self.widgets.box4);
             ..add_widget(&self.widgets.box5);
             ..add_widget(&self.widgets.box6);
@@ -77,8 +89,26 @@ impl Widget for SupportPanel {
             ..add_widget(&self.widgets.button2);
             ..add_widget(&self.widgets.button3);
             ..add_widget(&self.widgets.button4);
+            ..add_widget(&self.widgets.serial_button);
         };
 
+        // Shown once the serial number has been read.
+        self.widgets.serial_label.set_no_show_all(true);
+        self.widgets.serial_copy.set_no_show_all(true);
+
+        let log_windows = [
+            ("yesterday", fl!("log-window-yesterday")),
+            ("boot", fl!("log-window-boot")),
+            ("boots", fl!("log-window-boots")),
+            ("week", fl!("log-window-week")),
+        ];
+
+        for (id, label) in log_windows {
+            self.widgets.log_window.append(Some(id), &label);
+        }
+
+        self.widgets.log_window.set_active_id(Some("yesterday"));
+
         self.widgets.root.bin_clamp(300, 600, 80);
     }
 
@@ -96,15 +126,13 @@ impl Widget for SupportPanel {
                 info.operating_system = fl!("unknown");
             }
 
-            if info.serial_number.is_empty() {
-                info.serial_number = fl!("unknown");
-            }
-
-            stream.emit(SupportEvent::UpdateInfo(info));
+            stream.emit(SupportEvent::UpdateInfo(Box::new(info)));
         });
 
         SupportModel {
             vendor: None,
+            serial_number: None,
+            stream: relm.stream().clone(),
             window,
             log_dialog: None,
         }
@@ -113,24 +141,22 @@ impl Widget for SupportPanel {
     fn update(&mut self, event: SupportEvent) {
         match event {
             SupportEvent::UpdateInfo(info) => {
-                let serial_number_row = self.widgets.settings_box.row_at_index(1).unwrap();
-                serial_number_row.show();
-
                 self.components
                     .model_info
                     .emit(InfoLabelEvent::SetLabel(info.model_and_version));
 
-                self.components
-                    .serial_info
-                    .emit(InfoLabelEvent::SetLabel(info.serial_number));
-
                 self.components
                     .os_info
                     .emit(InfoLabelEvent::SetLabel(info.operating_system));
 
+                self.update_hardware(&info.hardware);
+
                 self.model.vendor = info.vendor;
 
-                serial_number_row.hide();
+                // Known already if the panel is running as root.
+                if !info.serial_number.is_empty() {
+                    self.show_serial(info.serial_number);
+                }
 
                 let set_by_resource = |resource: &str| {
                     let pixbuf = gdk_pixbuf::Pixbuf::from_resource_at_scale(
@@ -162,6 +188,37 @@ impl Widget for SupportPanel {
 
             SupportEvent::CommunitySupport => open_url("https://chat.pop-os.org"),
 
+            SupportEvent::CopySerial => {
+                if let Some(serial) = self.model.serial_number.as_deref() {
+                    gtk::Clipboard::get(&gtk::gdk::SELECTION_CLIPBOARD).set_text(serial);
+                }
+            }
+
+            SupportEvent::ReadSerial => {
+                self.widgets.serial_button.set_sensitive(false);
+
+                let stream = self.model.stream.clone();
+                let (_channel, sender) =
+                    relm::Channel::new(move |result| stream.emit(SupportEvent::SerialRead(result)));
+
+                std::thread::spawn(move || {
+                    let _ = sender.send(read_serial_subprocess());
+                });
+            }
+
+            SupportEvent::SerialRead(result) => match result {
+                Ok(serial) if !serial.is_empty() => self.show_serial(serial),
+                Ok(_) => {
+                    self.widgets.serial_button.hide();
+                    self.widgets.serial_label.set_text(&fl!("unknown"));
+                    self.widgets.serial_label.show();
+                }
+                Err(why) => {
+                    eprintln!("failed to read serial number: {:?}", why);
+                    self.widgets.serial_button.set_sensitive(true);
+                }
+            },
+
             SupportEvent::CreateSupportTicket => match self.model.vendor {
                 Some(Vendor::System76) => {
                     open_url("https://system76.com/my-account/support-tickets/new")
@@ -204,8 +261,15 @@ impl Widget for SupportPanel {
                 let (_channel, sender) =
                     relm::Channel::new(move |result| stream.emit(LogEvent::GeneratedLogs(result)));
 
+                let window = match self.widgets.log_window.active_id().as_deref() {
+                    Some("boot") => TimeWindow::Boots(1),
+                    Some("boots") => TimeWindow::Boots(3),
+                    Some("week") => TimeWindow::days_ago(7),
+                    _ => TimeWindow::default(),
+                };
+
                 std::thread::spawn(move || {
-                    let _ = sender.send(generate_logs_subprocess());
+                    let _ = sender.send(generate_logs_subprocess(&window));
                 });
 
                 // Keeps the event stream alive for as long as the dialog needs it.
@@ -239,12 +303,51 @@ impl Widget for SupportPanel {
                     #[name="model_info"]
                     InfoLabel(fl!("model-and-version")),
 
-                    #[name="serial_info"]
-                    InfoLabel(fl!("serial-number")),
+                    #[name="serial_box"]
+                    InfoBox {
+                        Description(fl!("serial-number")),
+
+                        #[name="serial_label"]
+                        gtk::Label {
+                            halign: gtk::Align::End,
+                            valign: gtk::Align::Center,
+                            selectable: true,
+                        },
+
+                        #[name="serial_copy"]
+                        gtk::Button {
+                            label: &fl!("serial-number-copy"),
+                            clicked => SupportEvent::CopySerial,
+                        },
+
+                        #[name="serial_button"]
+                        gtk::Button {
+                            label: &fl!("serial-number-button"),
+                            clicked => SupportEvent::ReadSerial,
+                        }
+                    },
 
                     #[name="os_info"]
                     InfoLabel(fl!("os-version")),
 
+                    #[name="processor_info"]
+                    InfoLabel(fl!("processor")),
+
+                    #[name="memory_info"]
+                    InfoLabel(fl!("memory")),
+
+                    #[name="graphics_info"]
+                    InfoLabel(fl!("graphics")),
+
+                    #[name="storage_info"]
+                    InfoLabel(fl!("storage")),
+
+                    #[name="firmware_info"]
+                    InfoLabel(fl!("firmware")),
+
+                    #[name="battery_info"]
+                    InfoLabel(fl!("battery")),
+
                     #[name="box4"]
                     InfoBox {
                         Description(fl!("documentation")),
@@ -282,6 +385,11 @@ impl Widget for SupportPanel {
                     InfoBox {
                         Description(fl!("create-logs")),
 
+                        #[name="log_window"]
+                        gtk::ComboBoxText {
+                            valign: gtk::Align::Center,
+                        },
+
                         #[name="button4"]
                         gtk::Button {
                             label: &fl!("create-logs-button"),
@@ -294,27 +402,142 @@ impl Widget for SupportPanel {
     }
 }
 
-pub fn generate_logs_subprocess() -> anyhow::Result<String> {
+pub fn generate_logs_subprocess(window: &TimeWindow) -> anyhow::Result<LogArchive> {
     let home_dir = dirs::home_dir().context("no home directory")?;
 
+    // The helper runs as root, so data from the session is collected here.
+    let user_data = tempfile::tempdir().context("failed to create directory for user data")?;
+
+    if let Err(why) = smol::block_on(logs::collect_session(user_data.path(), window)) {
+        eprintln!("failed to collect data from the user session: {:?}", why);
+    }
+
     std::process::Command::new("pkexec")
         .arg("pop-support")
         .arg("generate-logs")
         .arg(home_dir)
+        .args(window.to_args())
+        .arg("--user-data")
+        .arg(user_data.path())
         .output()
         .context("failed to start command to generate logs")
         .and_then(|output| {
             let output = String::from_utf8(output.stdout)
                 .context("output of command to generate logs is corrupted")?;
 
-            let path = dbg!(&output)
-                .strip_prefix("PATH ")
-                .context("command that generated logs did not provide path to logs")?;
+            let mut path = None;
+            let mut encrypted_for = Vec::new();
 
-            Ok(path.trim().to_owned())
+            for line in output.lines() {
+                if let Some(value) = line.strip_prefix("PATH ") {
+                    path = Some(value.trim().to_owned());
+                } else if let Some(value) = line.strip_prefix("ENCRYPTED_FOR ") {
+                    encrypted_for.push(value.trim().to_owned());
+                }
+            }
+
+            let path = path.context("command that generated logs did not provide path to logs")?;
+
+            Ok(LogArchive {
+                path,
+                encrypted_for,
+            })
         })
 }
 
+impl SupportPanel {
+    fn update_hardware(&mut self, hardware: &Hardware) {
+        let processor = hardware.cpu.as_ref().map(|cpu| {
+            fl!(
+                "processor-cores",
+                model = cpu.model.clone(),
+                cores = cpu.cores,
+                threads = cpu.threads
+            )
+        });
+
+        let memory = hardware.memory.map(support_info::format_size);
+
+        let graphics = join_lines(hardware.gpus.iter().map(ToString::to_string));
+
+        let storage = join_lines(hardware.storage.iter().map(|device| {
+            let name = if device.model.is_empty() {
+                &device.name
+            } else {
+                &device.model
+            };
+
+            format!("{} ({})", name, support_info::format_size(device.size))
+        }));
+
+        let firmware = Some(hardware.firmware.to_string()).filter(|f| !f.is_empty());
+
+        let battery = join_lines(
+            hardware
+                .batteries
+                .iter()
+                .map(|battery| fl!("battery-health", health = battery.health())),
+        );
+
+        let rows = [
+            (&self.components.processor_info, processor),
+            (&self.components.memory_info, memory),
+            (&self.components.graphics_info, graphics),
+            (&self.components.storage_info, storage),
+            (&self.components.firmware_info, firmware),
+            (&self.components.battery_info, battery),
+        ];
+
+        for (component, value) in rows {
+            // Rows are hidden for hardware which is absent or could not be read.
+            if let Some(row) = component.widget().parent() {
+                row.set_visible(value.is_some());
+            }
+
+            if let Some(value) = value {
+                component.emit(InfoLabelEvent::SetLabel(value));
+            }
+        }
+    }
+
+    fn show_serial(&mut self, serial: String) {
+        self.widgets.serial_button.hide();
+        self.widgets.serial_label.set_text(&serial);
+        self.widgets.serial_label.show();
+        self.widgets.serial_copy.show();
+        self.model.serial_number = Some(serial);
+    }
+}
+
+/// Reads the serial number through the privileged helper, which requires authorization.
+pub fn read_serial_subprocess() -> anyhow::Result<String> {
+    let output = std::process::Command::new("pkexec")
+        .arg("pop-support")
+        .arg("serial")
+        .output()
+        .context("failed to start command to read serial number")?;
+
+    if !output.status.success() {
+        anyhow::bail!("command to read serial number failed: {}", output.status);
+    }
+
+    let serial = String::from_utf8(output.stdout)
+        .context("output of command to read serial number is corrupted")?;
+
+    Ok(serial.trim().to_owned())
+}
+
+/// Joins values onto separate lines, or `None` if there are none.
+fn join_lines(values: impl Iterator<Item = String>) -> Option<String> {
+    let lines = values.collect::<Vec<_>>();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
 fn open_url(url: &'static str) {
     std::thread::spawn(move || {
         let _ = std::process::Command::new("xdg-open").arg(url).status();
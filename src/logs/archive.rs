@@ -0,0 +1,211 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Writes log archives in-process, without depending on `tar` or `xz` binaries.
+
+use super::encrypt::Output;
+use super::files_in;
+use anyhow::Context;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::str::FromStr;
+use xz2::write::XzEncoder;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Format {
+    #[default]
+    Xz,
+    Zstd,
+    Gzip,
+    Zip,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Xz => "tar.xz",
+            Format::Zstd => "tar.zst",
+            Format::Gzip => "tar.gz",
+            Format::Zip => "zip",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "xz" => Ok(Format::Xz),
+            "zstd" => Ok(Format::Zstd),
+            "gzip" => Ok(Format::Gzip),
+            "zip" => Ok(Format::Zip),
+            _ => Err(anyhow::anyhow!(
+                "unsupported archive format: expected xz, zstd, gzip, or zip"
+            )),
+        }
+    }
+}
+
+pub enum Archive {
+    Tar(tar::Builder<Compressor>),
+
+    /// Zip archives require seeking, so they are assembled in an anonymous
+    /// temporary file before being copied to the output.
+    Zip(ZipWriter<File>, Output),
+}
+
+/// Compresses a tar archive as it is being written.
+pub enum Compressor {
+    Xz(XzEncoder<Output>),
+    Zstd(zstd::Encoder<'static, Output>),
+    Gzip(GzEncoder<Output>),
+}
+
+impl Compressor {
+    fn finish(self) -> io::Result<Output> {
+        match self {
+            Compressor::Xz(encoder) => encoder.finish(),
+            Compressor::Zstd(encoder) => encoder.finish(),
+            Compressor::Gzip(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl Write for Compressor {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Compressor::Xz(encoder) => encoder.write(buf),
+            Compressor::Zstd(encoder) => encoder.write(buf),
+            Compressor::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Compressor::Xz(encoder) => encoder.flush(),
+            Compressor::Zstd(encoder) => encoder.flush(),
+            Compressor::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+impl Archive {
+    pub fn new(output: Output, format: Format) -> anyhow::Result<Self> {
+        let compressor = match format {
+            Format::Xz => Compressor::Xz(XzEncoder::new(output, 6)),
+            Format::Zstd => Compressor::Zstd(
+                zstd::Encoder::new(output, 0).context("failed to create zstd encoder")?,
+            ),
+            Format::Gzip => {
+                Compressor::Gzip(GzEncoder::new(output, flate2::Compression::default()))
+            }
+            Format::Zip => {
+                let temp = tempfile::tempfile().context("failed to create temporary zip")?;
+                return Ok(Archive::Zip(ZipWriter::new(temp), output));
+            }
+        };
+
+        Ok(Archive::Tar(tar::Builder::new(compressor)))
+    }
+
+    /// Appends every file within a directory, named relative to that directory.
+    pub fn append_dir(&mut self, dir: &Path) -> anyhow::Result<()> {
+        for path in files_in(dir) {
+            let name = path.strip_prefix(dir).unwrap_or(&path);
+            let name = name.to_str().context("file name is not UTF-8")?;
+
+            eprintln!("archiving {}", name);
+
+            self.append_file(name, &path)
+                .with_context(|| format!("failed to archive {}", name))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn append_file(&mut self, name: &str, path: &Path) -> anyhow::Result<()> {
+        match self {
+            Archive::Tar(builder) => builder
+                .append_path_with_name(path, name)
+                .context("failed to append file to tar"),
+
+            Archive::Zip(writer, _) => {
+                let mut file = File::open(path).context("failed to open file")?;
+
+                writer
+                    .start_file(name, zip_options())
+                    .context("failed to append file to zip")?;
+
+                io::copy(&mut file, writer)
+                    .map(|_| ())
+                    .context("failed to write file to zip")
+            }
+        }
+    }
+
+    /// Appends data generated in memory, without writing it to a file first.
+    pub fn append_data(&mut self, name: &str, data: &[u8]) -> anyhow::Result<()> {
+        match self {
+            Archive::Tar(builder) => {
+                let mtime = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |time| time.as_secs());
+
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(mtime);
+                header.set_cksum();
+
+                builder
+                    .append_data(&mut header, name, data)
+                    .context("failed to append data to tar")
+            }
+
+            Archive::Zip(writer, _) => {
+                writer
+                    .start_file(name, zip_options())
+                    .context("failed to append data to zip")?;
+
+                writer
+                    .write_all(data)
+                    .context("failed to write data to zip")
+            }
+        }
+    }
+
+    /// Completes the archive and ensures that it has been written to disk.
+    pub fn finish(self) -> anyhow::Result<()> {
+        let output = match self {
+            Archive::Tar(builder) => builder
+                .into_inner()
+                .and_then(Compressor::finish)
+                .context("failed to finish tar")?,
+
+            Archive::Zip(mut writer, mut output) => {
+                let mut temp = writer.finish().context("failed to finish zip")?;
+
+                temp.seek(SeekFrom::Start(0))
+                    .and_then(|_| io::copy(&mut temp, &mut output))
+                    .context("failed to copy zip to output")?;
+
+                output
+            }
+        };
+
+        let file = output.finish().context("failed to finish encryption")?;
+
+        file.sync_all().context("failed to sync archive to disk")
+    }
+}
+
+fn zip_options() -> FileOptions {
+    FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o644)
+}
@@ -0,0 +1,326 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Declarative manifests describing what goes into a log archive.
+//!
+//! Manifests are TOML files with a `.toml` extension, read from
+//! [`VENDOR_DIR`] and [`ADMIN_DIR`]. A file in the admin directory replaces
+//! the vendor file of the same name, so an empty file disables it entirely.
+//! Files are applied in the lexical order of their names.
+
+use super::encrypt::Recipient;
+use super::redact::Rule;
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+/// Manifests shipped with the package.
+pub const VENDOR_DIR: &str = "/usr/share/pop-support/collectors.d";
+
+/// Manifests provided by the administrator of the system.
+pub const ADMIN_DIR: &str = "/etc/pop-support/collectors.d";
+
+/// How long a collector may run when its manifest does not specify a timeout.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Used when no manifests have been installed on the system.
+const DEFAULT_MANIFESTS: &[&str] = &[
+    include_str!("../../data/collectors.d/10-default.toml"),
+    include_str!("../../data/collectors.d/20-session.toml"),
+    include_str!("../../data/collectors.d/30-audio.toml"),
+    include_str!("../../data/collectors.d/30-crashes.toml"),
+    include_str!("../../data/collectors.d/30-firmware.toml"),
+    include_str!("../../data/collectors.d/30-graphics.toml"),
+    include_str!("../../data/collectors.d/30-network.toml"),
+    include_str!("../../data/collectors.d/30-packages.toml"),
+    include_str!("../../data/collectors.d/30-storage.toml"),
+    include_str!("../../data/collectors.d/30-units.toml"),
+];
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub collector: Vec<Collector>,
+
+    #[serde(default)]
+    pub redact: Vec<Rule>,
+
+    #[serde(default)]
+    pub recipient: Vec<Recipient>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Collector {
+    /// Where the collected data will be stored in the archive.
+    pub name: String,
+
+    /// Explains what the collected data is for.
+    #[serde(default)]
+    pub description: String,
+
+    /// Seconds to wait for the collector before giving up on it.
+    pub timeout: Option<u64>,
+
+    #[serde(default)]
+    pub phase: Phase,
+
+    #[serde(flatten)]
+    pub source: Source,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Source {
+    /// Stores the output of a command.
+    Command {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        stderr: Stderr,
+        /// Appended to the arguments when the start of the time window is known,
+        /// with `{}` replaced by seconds since the Unix epoch, such as `--since=@{}`.
+        since: Option<String>,
+    },
+
+    /// Copies a file, or every file in a directory.
+    Copy { path: PathBuf },
+
+    /// Stores the output of `journalctl` within the selected time window.
+    Journal {
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        stderr: Stderr,
+    },
+
+    /// Copies a log file, and its rotations modified within the selected time window.
+    ///
+    /// With `concatenate`, the rotations are decompressed and joined into one
+    /// file, from oldest to newest, rather than being stored separately.
+    Log {
+        path: PathBuf,
+        #[serde(default)]
+        concatenate: bool,
+    },
+
+    /// Copies every file matching the patterns, such as `/sys/class/drm/card*-*/status`,
+    /// stored beneath the name of the collector by their full path, or by their
+    /// path within the home directory for patterns beginning with `~/`.
+    Glob { paths: Vec<String> },
+
+    /// Records the values of environment variables which are set.
+    Environment { variables: Vec<String> },
+
+    /// Runs a command once for each item listed by another command.
+    ForEach(ForEach),
+
+    /// Lists packages which dpkg has not finished installing or removing.
+    BrokenPackages,
+
+    /// Lists installed packages whose version is not available from an
+    /// archive with one of the `official` origins.
+    ForeignPackages { official: Vec<String> },
+
+    /// Copies crash reports modified within the selected time window, keeping
+    /// only their single-line fields unless `full` is set.
+    CrashReports {
+        path: PathBuf,
+        #[serde(default)]
+        full: bool,
+    },
+}
+
+/// Runs `command` for each item listed by `list`, such as each block device.
+///
+/// An item is the first word of each line printed by `list`. Occurrences of
+/// `{}` in the arguments are replaced by the item, and the output is stored
+/// beneath the name of the collector, named by the item.
+#[derive(Debug, Deserialize)]
+pub struct ForEach {
+    /// The command and arguments which list the items.
+    pub list: Vec<String>,
+
+    /// A regular expression which items must match.
+    pub filter: Option<String>,
+
+    pub command: String,
+
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    #[serde(default)]
+    pub stderr: Stderr,
+}
+
+/// Which process runs a collector.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    /// Run as root by the privileged helper.
+    #[default]
+    System,
+    /// Run as the user who requested the logs, within their session.
+    User,
+}
+
+/// Where the standard error of a command is stored.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stderr {
+    /// Stored beside the standard output with a `.stderr` extension, unless empty.
+    #[default]
+    Separate,
+    /// Interleaved with the standard output in the same file.
+    Merge,
+    Discard,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Command { command, args, .. } => {
+                f.write_str(command)?;
+
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+
+                Ok(())
+            }
+
+            Source::Journal { args, .. } => {
+                f.write_str("journalctl")?;
+
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+
+                Ok(())
+            }
+
+            Source::Copy { path }
+            | Source::Log { path, .. }
+            | Source::CrashReports { path, .. } => {
+                write!(f, "{}", path.display())
+            }
+
+            Source::Glob { paths } => f.write_str(&paths.join(" ")),
+
+            Source::Environment { variables } => f.write_str(&variables.join(" ")),
+
+            Source::BrokenPackages => f.write_str("/var/lib/dpkg/status"),
+
+            Source::ForeignPackages { .. } => f.write_str("/var/lib/apt/lists"),
+
+            Source::ForEach(each) => {
+                write!(f, "{} | {}", each.list.join(" "), each.command)?;
+
+                for arg in &each.args {
+                    write!(f, " {}", arg)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Collector {
+    pub fn timeout(&self) -> Duration {
+        self.timeout.map_or(DEFAULT_TIMEOUT, Duration::from_secs)
+    }
+
+    /// Ensures that the name cannot escape the directory that logs are collected into.
+    fn is_contained(&self) -> bool {
+        !self.name.is_empty()
+            && Path::new(&self.name)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+    }
+}
+
+impl Manifest {
+    /// Parses a manifest from its TOML representation.
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        toml::from_str(input).context("manifest is not valid")
+    }
+
+    /// Loads and combines every manifest installed on the system.
+    pub fn load() -> Self {
+        let mut files = BTreeMap::new();
+
+        for dir in &[VENDOR_DIR, ADMIN_DIR] {
+            manifests_in(Path::new(dir), &mut files);
+        }
+
+        let mut combined = Manifest::default();
+
+        if files.is_empty() {
+            eprintln!("no collector manifests found: using built-in defaults");
+
+            for input in DEFAULT_MANIFESTS {
+                combined.extend(Self::parse(input).expect("built-in manifest is invalid"));
+            }
+
+            return combined;
+        }
+
+        for path in files.values() {
+            let result = std::fs::read_to_string(path)
+                .context("failed to read manifest")
+                .and_then(|input| Self::parse(&input));
+
+            match result {
+                Ok(manifest) => combined.extend(manifest),
+                Err(why) => eprintln!("skipping {}: {:?}", path.display(), why),
+            }
+        }
+
+        combined
+    }
+
+    /// Collectors which run in the given phase.
+    pub fn collectors(&self, phase: Phase) -> impl Iterator<Item = &Collector> {
+        self.collector
+            .iter()
+            .filter(move |collector| collector.phase == phase)
+    }
+
+    fn extend(&mut self, manifest: Manifest) {
+        for collector in manifest.collector {
+            if collector.is_contained() {
+                self.collector.push(collector);
+            } else {
+                eprintln!("skipping collector with invalid name: {}", collector.name);
+            }
+        }
+
+        self.redact.extend(manifest.redact);
+        self.recipient.extend(manifest.recipient);
+    }
+}
+
+/// Records the manifests in a directory, replacing those with the same file name.
+fn manifests_in(dir: &Path, files: &mut BTreeMap<String, PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+
+        if path.extension() != Some(OsStr::new("toml")) {
+            continue;
+        }
+
+        if let Some(name) = entry.file_name().to_str() {
+            files.insert(name.to_owned(), path);
+        }
+    }
+}
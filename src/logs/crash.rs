@@ -0,0 +1,131 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Collects the crash reports written by apport, which may be very large as
+//! they embed core dumps. By default, only their single-line fields are kept.
+
+use super::{check_deadline, copy_until};
+use anyhow::Context;
+use nix::unistd::Uid;
+use std::ffi::OsStr;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::path::Path;
+use std::time::{Instant, SystemTime};
+
+/// Copies each report in `dir` which was modified since the given time into `dest`.
+///
+/// Unless `full` is set, multi-line fields such as `CoreDump` and `ProcMaps`
+/// are left out, keeping the executable, package, signal, and other metadata.
+///
+/// The directory is writable by everyone, and reports belong to the user whose
+/// program crashed, so only regular files owned by root or `user` are read.
+///
+/// Stops once the deadline has passed, keeping the reports written so far.
+pub fn collect(
+    dir: &Path,
+    dest: &Path,
+    full: bool,
+    since: Option<SystemTime>,
+    user: Uid,
+    deadline: Instant,
+) -> anyhow::Result<()> {
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?;
+
+    std::fs::create_dir_all(dest).context("failed to create directory for crash reports")?;
+
+    for entry in entries.filter_map(Result::ok) {
+        check_deadline(deadline)?;
+
+        let path = entry.path();
+
+        if path.extension() != Some(OsStr::new("crash")) {
+            continue;
+        }
+
+        if let (Some(since), Ok(modified)) = (since, entry.metadata().and_then(|m| m.modified())) {
+            if modified < since {
+                continue;
+            }
+        }
+
+        let dest = dest.join(entry.file_name());
+
+        let result = open(&path, user).and_then(|mut report| {
+            if full {
+                let mut output = File::create(&dest).context("failed to create report")?;
+                copy_until(&mut report, &mut output, deadline).context("failed to copy report")
+            } else {
+                headers(report, &dest, deadline)
+            }
+        });
+
+        if let Err(why) = result {
+            // A partially written report is left for redaction, but the rest are skipped.
+            if is_timeout(&why) {
+                return Err(why);
+            }
+
+            eprintln!("skipping crash report {}: {:?}", path.display(), why);
+        }
+    }
+
+    Ok(())
+}
+
+/// Opens a report without following symbolic links, if it is owned by root or the user.
+fn open(path: &Path, user: Uid) -> anyhow::Result<File> {
+    let report = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+        .open(path)
+        .context("failed to open report")?;
+
+    let metadata = report
+        .metadata()
+        .context("failed to get metadata of report")?;
+
+    if !metadata.is_file() {
+        anyhow::bail!("not a regular file");
+    }
+
+    if metadata.uid() != 0 && metadata.uid() != user.as_raw() {
+        anyhow::bail!("owned by another user");
+    }
+
+    Ok(report)
+}
+
+/// Writes the fields of a report which fit on one line.
+fn headers(report: File, dest: &Path, deadline: Instant) -> anyhow::Result<()> {
+    let mut output = File::create(dest).context("failed to create report")?;
+
+    for line in BufReader::new(report).split(b'\n').map_while(Result::ok) {
+        check_deadline(deadline)?;
+
+        // Continuation lines of multi-line fields begin with a space.
+        if line.starts_with(b" ") {
+            continue;
+        }
+
+        // Multi-line fields have nothing after the colon of their first line.
+        if line.ends_with(b":") {
+            continue;
+        }
+
+        output
+            .write_all(&line)
+            .and_then(|_| output.write_all(b"\n"))
+            .context("failed to write report")?;
+    }
+
+    Ok(())
+}
+
+fn is_timeout(why: &anyhow::Error) -> bool {
+    why.chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|cause| cause.kind() == io::ErrorKind::TimedOut)
+}
@@ -0,0 +1,90 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Encrypts log archives with [age](https://age-encryption.org/) so that only
+//! the support staff of the vendor can read them.
+
+use crate::Vendor;
+use age::stream::StreamWriter;
+use anyhow::Context;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{self, Write};
+
+/// A public key to encrypt archives to, as declared by `[[recipient]]` in a collector manifest.
+#[derive(Debug, Deserialize)]
+pub struct Recipient {
+    /// Who is able to decrypt the archive, as shown to the user.
+    pub label: String,
+
+    /// An age public key, beginning with `age1`.
+    pub key: String,
+
+    /// Only encrypts archives to this key on hardware from this vendor.
+    pub vendor: Option<Vendor>,
+}
+
+/// Selects the recipients which apply to hardware from the given vendor.
+pub fn recipients_for(recipients: &[Recipient], vendor: Option<Vendor>) -> Vec<&Recipient> {
+    recipients
+        .iter()
+        .filter(|recipient| recipient.vendor.is_none() || recipient.vendor == vendor)
+        .collect()
+}
+
+/// Where the archive is written to, encrypting it if there are recipients.
+pub enum Output {
+    Plain(File),
+    Encrypted(StreamWriter<File>),
+}
+
+impl Output {
+    pub fn new(file: File, recipients: &[&Recipient]) -> anyhow::Result<Self> {
+        if recipients.is_empty() {
+            return Ok(Output::Plain(file));
+        }
+
+        let mut keys = Vec::with_capacity(recipients.len());
+
+        for recipient in recipients {
+            let key = recipient
+                .key
+                .parse::<age::x25519::Recipient>()
+                .map_err(|why| anyhow::anyhow!("key of {} is invalid: {}", recipient.label, why))?;
+
+            keys.push(key);
+        }
+
+        let encryptor =
+            age::Encryptor::with_recipients(keys.iter().map(|key| key as &dyn age::Recipient))
+                .context("failed to encrypt to recipients")?;
+
+        encryptor
+            .wrap_output(file)
+            .map(Output::Encrypted)
+            .context("failed to write encryption header")
+    }
+
+    pub fn finish(self) -> io::Result<File> {
+        match self {
+            Output::Plain(file) => Ok(file),
+            Output::Encrypted(writer) => writer.finish(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Plain(file) => file.write(buf),
+            Output::Encrypted(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Plain(file) => file.flush(),
+            Output::Encrypted(writer) => writer.flush(),
+        }
+    }
+}
@@ -0,0 +1,758 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+mod archive;
+mod collector;
+mod crash;
+mod encrypt;
+mod network;
+mod packages;
+mod redact;
+mod report;
+mod requester;
+mod rotated;
+mod session;
+mod window;
+
+pub use self::archive::{Archive, Format};
+pub use self::collector::{Collector, ForEach, Manifest, Phase, Source, Stderr};
+pub use self::encrypt::{Output, Recipient};
+pub use self::redact::{Redactor, Rule};
+pub use self::report::{Report, Status, REPORT_FILE};
+pub use self::requester::Requester;
+pub use self::session::collect_session;
+pub use self::window::{parse_boot_id, TimeWindow};
+
+use crate::Vendor;
+use anyhow::Context;
+use nix::unistd::Uid;
+use smol::fs::File as AsyncFile;
+use smol::process::Command;
+use std::ffi::OsStr;
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use std::{fs::File, path::Path, process::ExitStatus, process::Stdio};
+
+#[derive(Debug)]
+pub struct Options {
+    /// Pseudonymise personally-identifiable information before archiving.
+    pub redact: bool,
+
+    /// Compression used by the archive.
+    pub format: Format,
+
+    /// The user who requested the logs, if they were requested through pkexec.
+    pub requester: Option<Requester>,
+
+    /// The span of time that journals and logs are collected from.
+    pub window: TimeWindow,
+
+    /// Data already collected from the session of the user, to include in the archive.
+    pub user_data: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            redact: true,
+            format: Format::default(),
+            requester: None,
+            window: TimeWindow::default(),
+            user_data: None,
+        }
+    }
+}
+
+/// A log archive which has been written to disk.
+#[derive(Debug)]
+pub struct LogArchive {
+    pub path: String,
+
+    /// Labels of the recipients which the archive was encrypted to, if any.
+    pub encrypted_for: Vec<String>,
+}
+
+pub async fn generate(home: &str, options: &Options) -> anyhow::Result<LogArchive> {
+    // Fail before collecting anything if the archive could not be written.
+    if let Some(requester) = &options.requester {
+        requester.check_destination(Path::new(home))?;
+    }
+
+    let tempdir = tempfile::tempdir().context("failed to fetch temporary directory")?;
+
+    async fn system_info() -> String {
+        crate::support_info::SupportInfo::fetch().await.text()
+    }
+
+    let temp = tempdir.path();
+
+    let manifest = Manifest::load();
+    let user = options
+        .requester
+        .as_ref()
+        .map_or_else(Uid::current, |requester| requester.uid);
+
+    let collectors = run(
+        manifest.collectors(Phase::System),
+        temp,
+        &options.window,
+        user,
+    );
+
+    let (reports, system_info) = futures::join!(collectors, system_info());
+
+    // Data from the session of the user is collected by the GUI before the
+    // helper is started, since the helper cannot access the session itself.
+    if let Some(user_data) = options.user_data.clone() {
+        let dest = temp.join(session::USER_DIR);
+        let uid = options.requester.as_ref().map(|requester| requester.uid);
+
+        let result = smol::unblock(move || session::import(&user_data, &dest, uid)).await;
+
+        if let Err(why) = result {
+            eprintln!(
+                "failed to import data from the session of the user: {:?}",
+                why
+            );
+        }
+    }
+
+    let mut system_info = system_info.into_bytes();
+    let mut reports = report::serialize(&reports)?;
+
+    if options.redact {
+        eprintln!("redacting personally-identifiable information");
+
+        let mut redactor = Redactor::new(&manifest.redact);
+        let dir = temp.to_owned();
+
+        redactor = smol::unblock(move || {
+            redactor.redact_dir(&dir);
+            redactor
+        })
+        .await;
+
+        system_info = redactor.redact(&system_info);
+        reports = redactor.redact(&reports);
+    }
+
+    let time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let recipients = encrypt::recipients_for(&manifest.recipient, Vendor::guess());
+
+    let mut extension = options.format.extension().to_owned();
+
+    if !recipients.is_empty() {
+        extension.push_str(".age");
+    }
+
+    let file_name = format!("pop-support_{:?}.{}", time, extension);
+    let log_path = format!("{home}/{file_name}");
+
+    let file = requester::create(Path::new(home), &file_name, options.requester.as_ref())
+        .context("failed to create log archive")?;
+    let format = options.format;
+    let dir = temp.to_owned();
+
+    let result = match Output::new(file, &recipients) {
+        Ok(output) => {
+            smol::unblock(move || {
+                let mut archive = Archive::new(output, format)?;
+                archive.append_dir(&dir)?;
+                archive.append_data("systeminfo.txt", &system_info)?;
+                archive.append_data(REPORT_FILE, &reports)?;
+                archive.finish()
+            })
+            .await
+        }
+
+        Err(why) => Err(why),
+    };
+
+    if let Err(why) = result {
+        let _ = std::fs::remove_file(&log_path);
+        return Err(why.context("failed to write log archive"));
+    }
+
+    Ok(LogArchive {
+        path: log_path,
+        encrypted_for: recipients.iter().map(|r| r.label.clone()).collect(),
+    })
+}
+
+/// What collectors are limited to.
+struct Scope<'a> {
+    window: &'a TimeWindow,
+
+    /// The start of the window, if it could be determined.
+    since: Option<SystemTime>,
+
+    /// The user who the archive is being generated for.
+    user: Uid,
+}
+
+/// Runs collectors concurrently, storing their output in the temporary directory.
+async fn run(
+    collectors: impl Iterator<Item = &Collector>,
+    temp: &Path,
+    window: &TimeWindow,
+    user: Uid,
+) -> Vec<Report> {
+    let scope = &Scope {
+        window,
+        since: window.start().await,
+        user,
+    };
+
+    let collectors = collectors.map(|collector| async move {
+        let start = Instant::now();
+        let mut executed = Vec::new();
+
+        let result = collect(collector, temp, scope, &mut executed).await;
+
+        let report = Report::new(
+            collector,
+            &executed,
+            start.elapsed(),
+            &temp.join(&collector.name),
+            &result,
+        );
+
+        if let Some(why) = &report.error {
+            eprintln!("{} ({}): {}", collector.name, collector.description, why);
+        }
+
+        report
+    });
+
+    futures::future::join_all(collectors).await
+}
+
+/// Runs a collector, storing its output in the temporary directory.
+///
+/// Commands return their exit status, for inclusion in the report, and the
+/// command lines which were run are added to `executed`.
+async fn collect(
+    collector: &Collector,
+    temp: &Path,
+    scope: &Scope<'_>,
+    executed: &mut Vec<String>,
+) -> anyhow::Result<Option<ExitStatus>> {
+    let Scope {
+        window,
+        since,
+        user,
+    } = *scope;
+
+    let name = collector.name.as_str();
+    let duration = collector.timeout();
+
+    match &collector.source {
+        Source::Command {
+            command: cmd,
+            args,
+            stderr,
+            since: since_arg,
+        } => {
+            let mut args = args.clone();
+
+            if let (Some(arg), Some(since)) = (since_arg, since) {
+                args.push(arg.replace("{}", &window::unix_secs(since).to_string()));
+            }
+
+            executed.push(report::command_line(cmd, &args));
+
+            command(cmd, &args, *stderr, duration, temp, name)
+                .await
+                .map(Some)
+        }
+
+        Source::Copy { path } => timeout(duration, copy(temp, expand_home(path), name))
+            .await
+            .map(|_| None),
+
+        Source::Journal { args, stderr } => {
+            let mut status = None;
+
+            for (part, window_args) in window.journal_args() {
+                let name = match part {
+                    Some(part) => [name, "/", &part].concat(),
+                    None => name.to_owned(),
+                };
+
+                let args = window_args
+                    .iter()
+                    .chain(args.iter())
+                    .map(String::as_str)
+                    .chain(std::iter::once("--no-pager"))
+                    .collect::<Vec<_>>();
+
+                executed.push(report::command_line("journalctl", &args));
+
+                let exit = command("journalctl", &args, *stderr, duration, temp, &name).await?;
+
+                // The first unsuccessful boot determines the status reported.
+                match status {
+                    Some(status) if !ExitStatus::success(&status) => (),
+                    _ => status = Some(exit),
+                }
+            }
+
+            Ok(status)
+        }
+
+        Source::Log { path, concatenate } => {
+            let path = expand_home(path);
+            log(temp, &path, since, name, *concatenate, duration)
+                .await
+                .map(|_| None)
+        }
+
+        Source::Glob { paths } => timeout(duration, glob(temp, paths, name))
+            .await
+            .map(|_| None),
+
+        Source::Environment { variables } => {
+            write_output(temp, name, &environment(variables)).map(|_| None)
+        }
+
+        Source::BrokenPackages => {
+            let output = timeout(duration, smol::unblock(packages::broken)).await?;
+            write_output(temp, name, &output).map(|_| None)
+        }
+
+        Source::ForeignPackages { official } => {
+            let official = official.clone();
+            let foreign = smol::unblock(move || packages::foreign(&official));
+            let output = timeout(duration, foreign).await?;
+            write_output(temp, name, &output).map(|_| None)
+        }
+
+        Source::ForEach(each) => for_each(each, duration, temp, name, executed).await,
+
+        Source::CrashReports { path, full } => {
+            let (path, dest, full) = (path.clone(), temp.join(name), *full);
+            let deadline = Instant::now() + duration;
+
+            // Waits for the reports to be written even when the deadline passes,
+            // so that nothing is written after the directory has been redacted.
+            smol::unblock(move || crash::collect(&path, &dest, full, since, user, deadline))
+                .await
+                .map(|_| None)
+        }
+    }
+}
+
+async fn command<A: AsRef<OsStr>>(
+    command: &str,
+    args: &[A],
+    stderr: Stderr,
+    duration: Duration,
+    temp: &Path,
+    filename: &str,
+) -> anyhow::Result<ExitStatus> {
+    eprintln!("fetching output from `{command}`");
+
+    let stdout = tempfile(temp, filename)?;
+    let stderr_name = [filename, ".stderr"].concat();
+
+    let stderr_file = match stderr {
+        Stderr::Separate => Stdio::from(tempfile(temp, &stderr_name)?),
+        Stderr::Merge => Stdio::from(stdout.try_clone().context("failed to share stdout")?),
+        Stderr::Discard => Stdio::null(),
+    };
+
+    // The command is killed if it does not exit in time, but its output so far is kept.
+    let status = Command::new(command)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(stderr_file)
+        .stdout(stdout)
+        .kill_on_drop(true)
+        .status();
+
+    let result = timeout(duration, async move {
+        status
+            .await
+            .with_context(|| format!("failed to execute {}", command))
+    })
+    .await;
+
+    if stderr == Stderr::Separate {
+        remove_if_empty(&temp.join(&stderr_name));
+    }
+
+    // Leaves nothing behind for commands which are not installed.
+    if result.is_err() {
+        remove_if_empty(&temp.join(filename));
+    }
+
+    result
+}
+
+/// Runs a command for each listed item, reporting the first unsuccessful exit status.
+/// The listing and each command are added to `executed`.
+async fn for_each(
+    each: &ForEach,
+    duration: Duration,
+    temp: &Path,
+    name: &str,
+    executed: &mut Vec<String>,
+) -> anyhow::Result<Option<ExitStatus>> {
+    let (list, list_args) = each.list.split_first().context("list command is empty")?;
+
+    executed.push(report::command_line(list, list_args));
+
+    let filter = match &each.filter {
+        Some(filter) => Some(regex::Regex::new(filter).context("invalid filter")?),
+        None => None,
+    };
+
+    let listing = Command::new(list)
+        .args(list_args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+
+    let listing = timeout(duration, async move {
+        listing
+            .await
+            .with_context(|| format!("failed to execute {}", list))
+    })
+    .await?;
+
+    let items = String::from_utf8_lossy(&listing.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        // Items name files, so must not be able to escape the directory.
+        .filter(|item| !item.contains('/') && *item != "." && *item != "..")
+        .filter(|item| match &filter {
+            Some(filter) => filter.is_match(item),
+            None => true,
+        })
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    // Nothing listed is not a failure, such as when no units have failed,
+    // unless the items could not be listed at all.
+    if items.is_empty() {
+        return Ok(Some(listing.status));
+    }
+
+    let tasks =
+        items
+            .iter()
+            .map(|item| {
+                let args = each
+                    .args
+                    .iter()
+                    .map(|arg| arg.replace("{}", item))
+                    .collect::<Vec<_>>();
+
+                executed.push(report::command_line(&each.command, &args));
+
+                let filename = [name, "/", item].concat();
+
+                async move {
+                    command(&each.command, &args, each.stderr, duration, temp, &filename).await
+                }
+            })
+            .collect::<Vec<_>>();
+
+    let mut status = None;
+
+    for result in futures::future::join_all(tasks).await {
+        let exit = result?;
+
+        match status {
+            Some(status) if !ExitStatus::success(&status) => (),
+            _ => status = Some(exit),
+        }
+    }
+
+    Ok(status)
+}
+
+fn remove_if_empty(path: &Path) {
+    if std::fs::metadata(path).map_or(true, |m| m.len() == 0) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Fails with [`io::ErrorKind::TimedOut`] if the future does not complete in time.
+async fn timeout<T>(
+    duration: Duration,
+    future: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    smol::future::or(future, async move {
+        smol::Timer::after(duration).await;
+
+        let message = format!("timed out after {} seconds", duration.as_secs());
+        Err(io::Error::new(io::ErrorKind::TimedOut, message).into())
+    })
+    .await
+}
+
+/// Copies until the reader is exhausted, failing if the deadline passes first.
+///
+/// A blocking task keeps running after its future is dropped by [`timeout`],
+/// so blocking work which writes to the temporary directory checks this
+/// between chunks instead, and is always awaited.
+fn copy_until(
+    reader: &mut impl io::Read,
+    writer: &mut impl Write,
+    deadline: Instant,
+) -> anyhow::Result<()> {
+    let mut buffer = vec![0; 64 * 1024];
+
+    loop {
+        check_deadline(deadline)?;
+
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(why) if why.kind() == io::ErrorKind::Interrupted => continue,
+            Err(why) => return Err(why).context("failed to read"),
+        };
+
+        writer
+            .write_all(&buffer[..read])
+            .context("failed to write")?;
+    }
+}
+
+/// Fails with [`io::ErrorKind::TimedOut`] once the deadline has passed.
+fn check_deadline(deadline: Instant) -> anyhow::Result<()> {
+    if Instant::now() >= deadline {
+        return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out").into());
+    }
+
+    Ok(())
+}
+
+/// Resolves paths beginning with `~/` against the home directory of the current user.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(relative), Some(home)) => home.join(relative),
+        _ => path.to_owned(),
+    }
+}
+
+/// Copies a log file, and each of its rotations (such as `syslog.1` or
+/// `syslog.2.gz`) which was last modified after the given time.
+///
+/// If `concatenate` is set, they are instead decompressed and joined into one
+/// file, from oldest to newest.
+async fn log(
+    temp: &Path,
+    path: &Path,
+    since: Option<SystemTime>,
+    name: &str,
+    concatenate: bool,
+    duration: Duration,
+) -> anyhow::Result<()> {
+    let rotations = rotated::rotations(path, since)?;
+
+    if concatenate {
+        let dest = tempfile(temp, name)?;
+        let deadline = Instant::now() + duration;
+
+        // Awaited in full, since the blocking task cannot be cancelled.
+        return smol::unblock(move || rotated::concatenate(&rotations, dest, deadline)).await;
+    }
+
+    let tasks = rotations.into_iter().map(|rotation| async move {
+        let dest = Path::new(name).join(&rotation.file_name);
+        copy(temp, &rotation.path, dest).await
+    });
+
+    let copies = async move {
+        futures::future::join_all(tasks)
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<()>>>()
+            .map(|_| ())
+    };
+
+    timeout(duration, copies).await
+}
+
+/// Copies every file matching the patterns, which tolerates some of them being unreadable.
+async fn glob(temp: &Path, patterns: &[String], name: &str) -> anyhow::Result<()> {
+    let mut tasks = Vec::new();
+
+    for pattern in patterns {
+        // Files in the home directory are named relative to it, so that the
+        // name of the user does not appear in the archive.
+        let base = match (pattern.starts_with("~/"), dirs::home_dir()) {
+            (true, Some(home)) => home,
+            _ => PathBuf::from("/"),
+        };
+
+        let pattern = expand_home(Path::new(pattern));
+        let pattern = pattern.to_str().context("pattern is not UTF-8")?;
+
+        let paths = glob::glob(pattern)
+            .with_context(|| format!("invalid pattern: {}", pattern))?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file());
+
+        for path in paths {
+            let dest = Path::new(name).join(path.strip_prefix(&base).unwrap_or(&path));
+
+            tasks.push(async move {
+                copy(temp, &path, dest)
+                    .await
+                    .with_context(|| format!("failed to copy {}", path.display()))
+            });
+        }
+    }
+
+    if tasks.is_empty() {
+        let message = format!("no files match {}", patterns.join(" "));
+        return Err(io::Error::new(io::ErrorKind::NotFound, message).into());
+    }
+
+    let total = tasks.len();
+    let mut errors = futures::future::join_all(tasks)
+        .await
+        .into_iter()
+        .filter_map(Result::err)
+        .collect::<Vec<_>>();
+
+    for why in &errors {
+        eprintln!("{}: {:?}", name, why);
+    }
+
+    // Attributes of devices which are powered off may not be readable, so
+    // this only fails if nothing could be copied.
+    if errors.len() == total {
+        return Err(errors.remove(0));
+    }
+
+    Ok(())
+}
+
+fn environment(variables: &[String]) -> String {
+    let mut output = String::new();
+
+    for variable in variables {
+        if let Some(value) = std::env::var_os(variable) {
+            output.push_str(&fomat_macros::fomat!((variable) "=" (value.to_string_lossy()) "\n"));
+        }
+    }
+
+    output
+}
+
+/// Stores output which was generated in-process.
+fn write_output(temp: &Path, name: &str, output: &str) -> anyhow::Result<()> {
+    tempfile(temp, name)?
+        .write_all(output.as_bytes())
+        .context("failed to write output")
+}
+
+async fn copy<D: AsRef<OsStr>, S: AsRef<OsStr>>(
+    tmp: &Path,
+    source: S,
+    name: D,
+) -> anyhow::Result<()> {
+    async fn copy_<D: AsRef<OsStr>, S: AsRef<OsStr>>(
+        tmp: &Path,
+        source: S,
+        name: D,
+    ) -> anyhow::Result<()> {
+        let source = Path::new(source.as_ref());
+        let dest = tmp.join(name.as_ref());
+
+        eprintln!(
+            "copying logs from {} to {}",
+            source.display(),
+            dest.display()
+        );
+
+        if let Some(parent) = dest.parent() {
+            let _ = smol::fs::create_dir_all(&parent).await;
+        }
+
+        let source = async move {
+            AsyncFile::open(source)
+                .await
+                .context("failed to open source")
+        };
+
+        let dest = async move {
+            AsyncFile::create(&dest)
+                .await
+                .context("failed to create dest")
+        };
+
+        let (mut source, mut dest) = futures::try_join!(source, dest)?;
+        smol::io::copy(&mut source, &mut dest)
+            .await
+            .context("failed to copy")
+            .map(|_| ())
+    }
+
+    let source = Path::new(source.as_ref());
+
+    if source.is_file() {
+        copy_(tmp, source, name).await
+    } else {
+        let dest = tmp.join(name.as_ref());
+
+        let repos = std::fs::read_dir(source).context("failed to read source")?;
+        let mut tasks = Vec::new();
+
+        for entry in repos.filter_map(Result::ok) {
+            if entry.metadata().map_or(false, |m| m.is_file()) {
+                let src = entry.path();
+                let dest = dest.join(entry.file_name());
+                if src.is_file() {
+                    tasks.push(async move {
+                        let src = src;
+                        copy_(tmp, &src, &dest).await
+                    });
+                }
+            }
+        }
+
+        let _ = futures::future::join_all(tasks).await;
+
+        Ok(())
+    }
+}
+
+fn tempfile(path: &Path, command: &str) -> anyhow::Result<File> {
+    let path = path.join(command);
+
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+
+    File::create(&path).with_context(|| format!("failed to create temporary file for {}", command))
+}
+
+/// Every file within a directory and its subdirectories, in a stable order.
+fn files_in(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.filter_map(Result::ok) {
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => files.extend(files_in(&entry.path())),
+                Ok(kind) if kind.is_file() => files.push(entry.path()),
+                _ => (),
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
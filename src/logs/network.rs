@@ -0,0 +1,150 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Reads the connection profiles of NetworkManager, so that the names of
+//! networks and their secrets can be redacted wherever they appear.
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+const CONNECTIONS_DIR: &str = "/etc/NetworkManager/system-connections";
+
+/// Values from connection profiles which identify the user, or must stay secret.
+#[derive(Debug, Default)]
+pub struct Identities {
+    /// Connection names and Wi-Fi SSIDs.
+    pub names: Vec<String>,
+
+    /// Wi-Fi passwords, VPN secrets, and private keys.
+    pub secrets: Vec<String>,
+}
+
+impl Identities {
+    pub fn load() -> Self {
+        let mut identities = Identities::default();
+
+        let entries = match std::fs::read_dir(CONNECTIONS_DIR) {
+            Ok(entries) => entries,
+            Err(_) => return identities,
+        };
+
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+
+            if path.extension() != Some(OsStr::new("nmconnection")) {
+                continue;
+            }
+
+            identities.read_profile(&path);
+        }
+
+        identities
+    }
+
+    fn read_profile(&mut self, path: &Path) {
+        if let Ok(profile) = std::fs::read_to_string(path) {
+            self.parse_profile(&profile);
+        }
+    }
+
+    /// Collects values from a profile in the keyfile format.
+    fn parse_profile(&mut self, profile: &str) {
+        let mut section = "";
+
+        for line in profile.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name;
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) if !value.trim().is_empty() => (key.trim(), value.trim()),
+                _ => continue,
+            };
+
+            if is_name(section, key) {
+                self.names.push(value.to_owned());
+            } else if is_secret(section, key) {
+                self.secrets.push(value.to_owned());
+            }
+        }
+    }
+}
+
+fn is_name(section: &str, key: &str) -> bool {
+    matches!((section, key), ("connection", "id") | ("wifi", "ssid"))
+}
+
+fn is_secret(section: &str, key: &str) -> bool {
+    const SECRET_KEYS: &[&str] = &[
+        "psk",
+        "password",
+        "private-key",
+        "private-key-password",
+        "preshared-key",
+        "secrets",
+        "wep-key0",
+        "wep-key1",
+        "wep-key2",
+        "wep-key3",
+    ];
+
+    // Keys such as `psk-flags` and `wep-key-type` describe how a secret is
+    // stored, and their values are small numbers rather than secrets.
+    if key.ends_with("-flags") || key.ends_with("-type") {
+        return false;
+    }
+
+    section == "vpn-secrets" || SECRET_KEYS.contains(&key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyfile() {
+        let profile = "\
+[connection]
+id=Home Network
+uuid=0b5e8d3a-1c2f-4e6a-9b7d-2f3e4a5b6c7d
+type=wifi
+
+[wifi]
+mode=infrastructure
+ssid=Home Network 5G
+
+[wifi-security]
+key-mgmt=wpa-psk
+psk=correct horse battery staple
+psk-flags=1
+wep-key-type=1
+wep-key0=0123456789
+
+[802-1x]
+password-flags=0
+private-key-password-flags=0
+
+[wireguard]
+private-key=yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=
+private-key-flags=0
+
+[vpn-secrets]
+password=hunter22
+";
+
+        let mut identities = Identities::default();
+        identities.parse_profile(profile);
+
+        assert_eq!(identities.names, ["Home Network", "Home Network 5G"]);
+        assert_eq!(
+            identities.secrets,
+            [
+                "correct horse battery staple",
+                "0123456789",
+                "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=",
+                "hunter22",
+            ]
+        );
+    }
+}
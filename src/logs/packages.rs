@@ -0,0 +1,227 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Detects installed packages which are in a broken state, or which were not
+//! installed from an official archive, by reading the databases of dpkg and apt.
+
+use anyhow::Context;
+use flate2::read::GzDecoder;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use xz2::read::XzDecoder;
+
+const DPKG_STATUS: &str = "/var/lib/dpkg/status";
+const APT_LISTS: &str = "/var/lib/apt/lists";
+
+/// A package known to dpkg.
+struct Package {
+    name: String,
+    version: String,
+    /// The third word of its `Status` field, such as `installed` or `half-configured`.
+    state: String,
+    /// The second word of its `Status` field, which is `reinstreq` if it must be reinstalled.
+    flag: String,
+}
+
+/// Lists packages which dpkg has not finished installing or removing, with their state.
+pub fn broken() -> anyhow::Result<String> {
+    let mut output = String::new();
+
+    for package in dpkg_packages()? {
+        let settled = matches!(
+            package.state.as_str(),
+            "installed" | "config-files" | "not-installed"
+        );
+
+        if !settled || package.flag == "reinstreq" {
+            output.push_str(&fomat_macros::fomat!(
+                (package.name) " " (package.version) " " (package.state) " " (package.flag) "\n"
+            ));
+        }
+    }
+
+    Ok(output)
+}
+
+/// Lists installed packages whose version is not available from an archive
+/// with one of the official origins, along with where it is available from.
+///
+/// Packages which are not available from any archive are listed as `local`.
+pub fn foreign(official: &[String]) -> anyhow::Result<String> {
+    let installed = dpkg_packages()?
+        .into_iter()
+        .filter(|package| package.state == "installed")
+        .map(|package| (package.name, package.version))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut official_versions = HashSet::new();
+    let mut other_origins = HashMap::new();
+    let mut unreadable = Vec::new();
+
+    let entries = std::fs::read_dir(APT_LISTS).context("failed to read apt lists")?;
+
+    let mut lists = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<_>>();
+
+    lists.sort();
+
+    for release in &lists {
+        let prefix = match release
+            .strip_suffix("InRelease")
+            .or_else(|| release.strip_suffix("Release"))
+        {
+            Some(prefix) => prefix,
+            None => continue,
+        };
+
+        let origin = match release_origin(&Path::new(APT_LISTS).join(release)) {
+            Some(origin) => origin,
+            None => continue,
+        };
+
+        let is_official = official.contains(&origin);
+
+        let packages = lists
+            .iter()
+            .filter(|list| list.starts_with(prefix) && list.contains("_Packages"));
+
+        for list in packages {
+            let path = Path::new(APT_LISTS).join(list);
+
+            let versions = match available(&path, &installed) {
+                Some(versions) => versions,
+                None => {
+                    unreadable.push(list.clone());
+                    continue;
+                }
+            };
+
+            for (name, version) in versions {
+                if is_official {
+                    official_versions.insert((name, version));
+                } else {
+                    other_origins
+                        .entry((name, version))
+                        .or_insert_with(|| origin.clone());
+                }
+            }
+        }
+    }
+
+    let mut output = String::new();
+
+    // Without these lists, some packages may wrongly be considered local.
+    for list in unreadable {
+        output.push_str(&fomat_macros::fomat!("# unable to read " (list) "\n"));
+    }
+
+    for (name, version) in installed {
+        let key = (name, version);
+
+        if official_versions.contains(&key) {
+            continue;
+        }
+
+        let origin = other_origins.get(&key).map_or("local", String::as_str);
+        let (name, version) = key;
+
+        output.push_str(&fomat_macros::fomat!((name) " " (version) " " (origin) "\n"));
+    }
+
+    Ok(output)
+}
+
+/// Every package in the dpkg database.
+fn dpkg_packages() -> anyhow::Result<Vec<Package>> {
+    let file = File::open(DPKG_STATUS).context("failed to open dpkg status")?;
+
+    let mut packages = Vec::new();
+
+    stanzas(BufReader::new(file), |fields| {
+        let status = fields.get("Status").map(String::as_str).unwrap_or_default();
+        let mut status = status.split_whitespace().skip(1);
+
+        packages.push(Package {
+            name: fields.get("Package").cloned().unwrap_or_default(),
+            version: fields.get("Version").cloned().unwrap_or_default(),
+            flag: status.next().unwrap_or_default().to_owned(),
+            state: status.next().unwrap_or_default().to_owned(),
+        });
+    });
+
+    Ok(packages)
+}
+
+/// Versions of installed packages which are available from a `Packages` list,
+/// or `None` if the list could not be read.
+fn available(path: &Path, installed: &BTreeMap<String, String>) -> Option<Vec<(String, String)>> {
+    let file = File::open(path).ok()?;
+
+    // Host names contain dots, so the compression is identified by what follows `_Packages`.
+    let name = path.file_name()?.to_str()?;
+
+    let reader: Box<dyn Read> = match name.rsplit_once("_Packages")?.1 {
+        "" => Box::new(file),
+        ".gz" => Box::new(GzDecoder::new(file)),
+        ".xz" => Box::new(XzDecoder::new(file)),
+        _ => return None,
+    };
+
+    let mut found = Vec::new();
+
+    stanzas(BufReader::new(reader), |fields| {
+        if let (Some(name), Some(version)) = (fields.get("Package"), fields.get("Version")) {
+            if installed.get(name) == Some(version) {
+                found.push((name.clone(), version.clone()));
+            }
+        }
+    });
+
+    Some(found)
+}
+
+/// The `Origin` field of a `Release` or `InRelease` file.
+fn release_origin(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .find_map(|line| {
+            line.strip_prefix("Origin:")
+                .map(|origin| origin.trim().to_owned())
+        })
+}
+
+/// Calls `func` with the single-line fields of each stanza of a deb822 file.
+fn stanzas<R: BufRead>(reader: R, mut func: impl FnMut(&HashMap<String, String>)) {
+    let mut fields = HashMap::new();
+
+    for line in reader.lines().map_while(Result::ok) {
+        if line.is_empty() {
+            if !fields.is_empty() {
+                func(&fields);
+                fields.clear();
+            }
+
+            continue;
+        }
+
+        // Continuation lines of multi-line fields are not needed.
+        if line.starts_with(' ') || line.starts_with('\t') {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            fields.insert(key.to_owned(), value.trim().to_owned());
+        }
+    }
+
+    if !fields.is_empty() {
+        func(&fields);
+    }
+}
@@ -0,0 +1,529 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Replaces personally-identifiable information in collected logs with pseudonyms.
+//!
+//! Every distinct value is assigned a token such as `[mac-1]`, and the same
+//! value is given the same token across every file in the archive. This keeps
+//! the logs useful for correlating events without revealing the value itself.
+
+use super::{files_in, network};
+use anyhow::Context;
+use flate2::read::MultiGzDecoder;
+use regex::bytes::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::Read;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+/// A pattern to redact, as declared by `[[redact]]` in a collector manifest.
+///
+/// If the pattern has a capture group, only the text matched by the first
+/// group is replaced, so that surrounding context such as a field name is kept.
+#[derive(Debug, Deserialize)]
+pub struct Rule {
+    /// Used as the prefix of the tokens which replace matched values.
+    pub name: String,
+    pub pattern: String,
+}
+
+const BUILTIN_RULES: &[(&str, &str)] = &[
+    ("mac", r"\b[0-9a-fA-F]{2}(?::[0-9a-fA-F]{2}){5}\b"),
+    (
+        "uuid",
+        r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b",
+    ),
+    (
+        "serial",
+        r"(?i)serial(?:[ _]?number)?\s*[:=]\s*(\S[^\r\n]*)",
+    ),
+    ("asset-tag", r"(?i)asset tag\s*:\s*(\S[^\r\n]*)"),
+    (
+        "secret",
+        r#"(?i)\b(?:psk|password|passwd|passphrase|secret|private-key|preshared-key|wep-key\d?)\s*[=:]\s*"?([^\s"]+)"#,
+    ),
+    // Fields such as `SSID: Home` from iw, or `ssid="Home"` from wpa_supplicant.
+    ("ssid", r#"(?i)\bssid\s*[=:]\s*['"]?([^'"\r\n]+)"#),
+    // Quoted in messages, such as `Trying to associate with SSID 'Home'`.
+    ("ssid", r#"(?i)\bssid\s+['"]([^'"\r\n]+)['"]"#),
+];
+
+/// Addresses are only redacted if they are public, since private addresses
+/// do not identify anyone and are needed to understand the network.
+const ADDRESS_RULES: &[(&str, &str, Filter)] = &[
+    (
+        "ip",
+        r"\b(?:25[0-5]|2[0-4]\d|1?\d?\d)(?:\.(?:25[0-5]|2[0-4]\d|1?\d?\d)){3}\b",
+        is_public_ipv4,
+    ),
+    (
+        "ip",
+        r"(?i)\b[23][0-9a-f]{0,3}:[0-9a-f:]*[0-9a-f]",
+        is_public_ipv6,
+    ),
+];
+
+/// Decides whether a matched value should be redacted.
+///
+/// It is given the value, and the text before and after it on the same line.
+type Filter = fn(&str, &[u8], &[u8]) -> bool;
+
+struct Pattern {
+    name: String,
+    regex: Regex,
+    applies: Filter,
+}
+
+pub struct Redactor {
+    rules: Vec<Pattern>,
+    pseudonyms: Pseudonyms,
+}
+
+#[derive(Default)]
+struct Pseudonyms {
+    tokens: HashMap<Vec<u8>, String>,
+    counters: HashMap<String, usize>,
+}
+
+/// Rules whose values are hexadecimal, which tools write in either case.
+const CASE_INSENSITIVE_RULES: &[&str] = &["ip", "mac", "uuid"];
+
+impl Pseudonyms {
+    fn get(&mut self, name: &str, value: &[u8]) -> String {
+        let value = if CASE_INSENSITIVE_RULES.contains(&name) {
+            value.to_ascii_lowercase()
+        } else {
+            value.to_owned()
+        };
+
+        if let Some(token) = self.tokens.get(&value) {
+            return token.clone();
+        }
+
+        let counter = self.counters.entry(name.to_owned()).or_insert(0);
+        *counter += 1;
+
+        let token = format!("[{}-{}]", name, counter);
+        self.tokens.insert(value, token.clone());
+        token
+    }
+}
+
+impl Redactor {
+    /// Combines the built-in rules with the identity of this system and custom rules.
+    pub fn new(custom: &[Rule]) -> Self {
+        let network = network::Identities::load();
+
+        let literals = [
+            ("hostname", hostname()),
+            ("user", usernames()),
+            ("network", network.names),
+            ("secret", network.secrets),
+        ];
+
+        Self::with_literals(&literals, custom)
+    }
+
+    /// Combines the built-in rules with rules matching the given words, and custom rules.
+    fn with_literals(literals: &[(&str, Vec<String>)], custom: &[Rule]) -> Self {
+        let mut rules = Vec::new();
+
+        // Literal values are replaced first, so that they are found in full.
+        for (name, words) in literals {
+            rules.extend(literal_rule(name, words));
+        }
+
+        for (name, pattern) in BUILTIN_RULES {
+            let regex = Regex::new(pattern).expect("invalid built-in rule");
+            rules.push(Pattern::new(name, regex));
+        }
+
+        for (name, pattern, applies) in ADDRESS_RULES {
+            rules.push(Pattern {
+                applies: *applies,
+                ..Pattern::new(name, Regex::new(pattern).expect("invalid built-in rule"))
+            });
+        }
+
+        for rule in custom {
+            match Regex::new(&rule.pattern) {
+                Ok(regex) => rules.push(Pattern::new(&rule.name, regex)),
+                Err(why) => eprintln!("skipping redaction rule {}: {}", rule.name, why),
+            }
+        }
+
+        Self {
+            rules,
+            pseudonyms: Pseudonyms::default(),
+        }
+    }
+
+    /// Replaces every sensitive value in the input with its pseudonym.
+    pub fn redact(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut output = input.to_owned();
+
+        for Pattern {
+            name,
+            regex,
+            applies,
+        } in &self.rules
+        {
+            let pseudonyms = &mut self.pseudonyms;
+            let input = std::mem::take(&mut output);
+
+            output = regex
+                .replace_all(&input, |caps: &Captures| {
+                    let whole = caps.get(0).unwrap();
+                    let value = caps.get(1).unwrap_or(whole);
+
+                    let before = &input[..value.start()];
+                    let before = match before.iter().rposition(|&b| b == b'\n') {
+                        Some(newline) => &before[newline + 1..],
+                        None => before,
+                    };
+
+                    let after = &input[value.end()..];
+                    let after = match after.iter().position(|&b| b == b'\n') {
+                        Some(newline) => &after[..newline],
+                        None => after,
+                    };
+
+                    let text = String::from_utf8_lossy(value.as_bytes());
+
+                    if !applies(&text, before, after) {
+                        return whole.as_bytes().to_owned();
+                    }
+
+                    match caps.get(1) {
+                        Some(value) => {
+                            let mut replaced =
+                                whole.as_bytes()[..value.start() - whole.start()].to_owned();
+                            replaced.extend_from_slice(
+                                pseudonyms.get(name, value.as_bytes()).as_bytes(),
+                            );
+                            replaced.extend_from_slice(
+                                &whole.as_bytes()[value.end() - whole.start()..],
+                            );
+                            replaced
+                        }
+                        None => pseudonyms.get(name, whole.as_bytes()).into_bytes(),
+                    }
+                })
+                .into_owned();
+        }
+
+        output
+    }
+
+    /// Redacts every file in a directory in place.
+    ///
+    /// Gzip-compressed files are decompressed so that their contents can be
+    /// redacted, and are stored without the `.gz` extension.
+    pub fn redact_dir(&mut self, dir: &Path) {
+        for path in files_in(dir) {
+            if let Err(why) = self.redact_file(&path) {
+                eprintln!("failed to redact {}: {:?}", path.display(), why);
+
+                // Never leave unredacted data behind to be archived.
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+
+    fn redact_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        let data = std::fs::read(path).context("failed to read file")?;
+
+        if path.extension() == Some(OsStr::new("gz")) {
+            let mut decompressed = Vec::new();
+
+            MultiGzDecoder::new(data.as_slice())
+                .read_to_end(&mut decompressed)
+                .context("failed to decompress file")?;
+
+            std::fs::remove_file(path).context("failed to remove compressed file")?;
+
+            return std::fs::write(path.with_extension(""), self.redact(&decompressed))
+                .context("failed to write decompressed file");
+        }
+
+        std::fs::write(path, self.redact(&data)).context("failed to write file")
+    }
+}
+
+impl Pattern {
+    fn new(name: &str, regex: Regex) -> Self {
+        Self {
+            name: name.to_owned(),
+            regex,
+            applies: |_, _, _| true,
+        }
+    }
+}
+
+/// Creates a rule which matches any of the given words.
+///
+/// Words are only matched in full, unless they begin or end with punctuation.
+/// Very short words, and numbers shorter than the minimum length of a WPA
+/// passphrase, are skipped, since they would match unrelated text throughout
+/// every file.
+fn literal_rule(name: &str, words: &[String]) -> Option<Pattern> {
+    const MIN_LITERAL_LEN: usize = 3;
+    const MIN_NUMERIC_LITERAL_LEN: usize = 8;
+
+    let is_short_number = |word: &str| {
+        word.len() < MIN_NUMERIC_LITERAL_LEN && word.bytes().all(|b| b.is_ascii_digit())
+    };
+
+    let is_word = |c: Option<char>| matches!(c, Some(c) if c.is_alphanumeric() || c == '_');
+
+    let alternatives = words
+        .iter()
+        .filter(|word| word.chars().count() >= MIN_LITERAL_LEN)
+        .filter(|word| !is_short_number(word))
+        .map(|word| {
+            let start = if is_word(word.chars().next()) {
+                "\\b"
+            } else {
+                ""
+            };
+            let end = if is_word(word.chars().last()) {
+                "\\b"
+            } else {
+                ""
+            };
+            [start, &regex::escape(word), end].concat()
+        })
+        .collect::<Vec<_>>();
+
+    if alternatives.is_empty() {
+        return None;
+    }
+
+    let pattern = ["(?:", &alternatives.join("|"), ")"].concat();
+
+    Regex::new(&pattern)
+        .ok()
+        .map(|regex| Pattern::new(name, regex))
+}
+
+fn is_public_ipv4(address: &str, before: &[u8], after: &[u8]) -> bool {
+    if is_version(before, after) {
+        return false;
+    }
+
+    let address = match address.parse::<Ipv4Addr>() {
+        Ok(address) => address,
+        Err(_) => return false,
+    };
+
+    let [a, b, ..] = address.octets();
+
+    // Carrier-grade NAT, which is shared rather than public.
+    let is_shared = a == 100 && (64..128).contains(&b);
+
+    !(address.is_private()
+        || address.is_loopback()
+        || address.is_link_local()
+        || address.is_broadcast()
+        || address.is_multicast()
+        || address.is_unspecified()
+        || address.is_documentation()
+        || is_shared
+        || a == 0)
+}
+
+fn is_public_ipv6(address: &str, _before: &[u8], _after: &[u8]) -> bool {
+    let address = match address.parse::<Ipv6Addr>() {
+        Ok(address) => address,
+        Err(_) => return false,
+    };
+
+    let segments = address.segments();
+
+    // Global unicast addresses are within 2000::/3, except for those in
+    // 2001:db8::/32, which are reserved for documentation.
+    segments[0] & 0xe000 == 0x2000 && !(segments[0] == 0x2001 && segments[1] == 0x0db8)
+}
+
+/// The hostname, unless it is the default of the distribution, which does not
+/// identify anyone and also appears in package names and archive URLs.
+/// Whether a dotted number is part of a version, such as `1:1.2.11.1-1ubuntu1`
+/// in a package list, or follows a field such as `Version:`.
+///
+/// A colon after the number is not considered, since it separates the port of an address.
+fn is_version(before: &[u8], after: &[u8]) -> bool {
+    const VERSION_PUNCTUATION: &[u8] = b"-~+";
+
+    if let Some(next) = after.first() {
+        if VERSION_PUNCTUATION.contains(next) {
+            return true;
+        }
+    }
+
+    if let Some(previous) = before.last() {
+        if VERSION_PUNCTUATION.contains(previous) || *previous == b':' {
+            return true;
+        }
+    }
+
+    let field = String::from_utf8_lossy(before).to_ascii_lowercase();
+    let field = field.trim_end().trim_end_matches([':', '=']);
+
+    field.trim_end().ends_with("version")
+}
+
+fn hostname() -> Vec<String> {
+    const DEFAULT_HOSTNAMES: &[&str] = &["localhost", "pop-os", "ubuntu"];
+
+    std::fs::read_to_string("/etc/hostname")
+        .map(|name| name.trim().to_owned())
+        .into_iter()
+        .filter(|name| !DEFAULT_HOSTNAMES.contains(&name.as_str()))
+        .collect()
+}
+
+/// Names of the accounts belonging to people rather than system services.
+fn usernames() -> Vec<String> {
+    let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+
+    passwd
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse::<u32>().ok()?;
+            (1000..65534).contains(&uid).then(|| name.to_owned())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact(input: &str) -> String {
+        let mut redactor = Redactor::with_literals(&[], &[]);
+        String::from_utf8(redactor.redact(input.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn mac_in_either_case() {
+        assert_eq!(
+            redact("3A:1F:00:b2:c4:d5 and 3a:1f:00:B2:C4:D5"),
+            "[mac-1] and [mac-1]"
+        );
+    }
+
+    #[test]
+    fn uuid() {
+        assert_eq!(
+            redact("UUID=0b5e8d3a-1c2f-4e6a-9b7d-2f3e4a5b6c7d ro"),
+            "UUID=[uuid-1] ro"
+        );
+    }
+
+    #[test]
+    fn public_ipv4() {
+        assert_eq!(redact("from 8.8.4.4 to 1.1.1.1"), "from [ip-1] to [ip-2]");
+    }
+
+    #[test]
+    fn private_ipv4() {
+        let input = "10.0.0.2 192.168.1.1 172.16.4.20 127.0.0.1 100.64.0.1";
+        assert_eq!(redact(input), input);
+    }
+
+    #[test]
+    fn versions_are_not_addresses() {
+        let input = "ii libfoo 1.2.11.1-1ubuntu1 amd64\nVersion: 8.4.4.1\nfoo 1:2.3.4.5 bar";
+        assert_eq!(redact(input), input);
+    }
+
+    #[test]
+    fn address_with_port() {
+        assert_eq!(redact("peer 8.8.4.4:51820"), "peer [ip-1]:51820");
+    }
+
+    #[test]
+    fn public_ipv6() {
+        assert_eq!(
+            redact("inet6 2606:4700:4700::1111/64 and 2606:4700:4700::1111"),
+            "inet6 [ip-1]/64 and [ip-1]"
+        );
+    }
+
+    #[test]
+    fn private_ipv6() {
+        let input = "inet6 fe80::1c2f:4eff:fe6a:9b7d/64 fd00::1 ::1 2001:db8::1";
+        assert_eq!(redact(input), input);
+    }
+
+    #[test]
+    fn serial_number() {
+        assert_eq!(
+            redact("\tSerial Number: NKM1234567\nserial=ABC123\n"),
+            "\tSerial Number: [serial-1]\nserial=[serial-2]\n"
+        );
+    }
+
+    #[test]
+    fn ssid() {
+        assert_eq!(
+            redact("\tSSID: Home Net\nssid=\"Home Net\"\nassociate with SSID 'Cafe'\n"),
+            "\tSSID: [ssid-1]\nssid=\"[ssid-1]\"\nassociate with SSID '[ssid-2]'\n"
+        );
+    }
+
+    #[test]
+    fn ssid_in_prose() {
+        let input = "wlp2s0: ssid scanning done";
+        assert_eq!(redact(input), input);
+    }
+
+    #[test]
+    fn literals_are_matched_as_words() {
+        let literals = [("hostname", vec!["thelio".to_owned()])];
+        let mut redactor = Redactor::with_literals(&literals, &[]);
+        let output = redactor.redact(b"thelio kernel: thelio-mira thelios");
+
+        assert_eq!(output, b"[hostname-1] kernel: [hostname-1]-mira thelios");
+    }
+
+    #[test]
+    fn short_and_numeric_literals_are_skipped() {
+        let words = vec![
+            "0".to_owned(),
+            "1".to_owned(),
+            "ab".to_owned(),
+            "1234".to_owned(),
+        ];
+        assert!(literal_rule("secret", &words).is_none());
+    }
+
+    #[test]
+    fn numeric_passphrases_are_redacted() {
+        let literals = [("secret", vec!["12345678".to_owned()])];
+        let mut redactor = Redactor::with_literals(&literals, &[]);
+        let output = redactor.redact(b"key 12345678 and 1234567");
+
+        assert_eq!(output, b"key [secret-1] and 1234567");
+    }
+
+    #[test]
+    fn network_secrets() {
+        let secrets = [
+            "correct horse battery staple",
+            "0123456789",
+            "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=",
+            "hunter22",
+        ];
+
+        let literals = [("secret", secrets.iter().map(|s| s.to_string()).collect())];
+        let mut redactor = Redactor::with_literals(&literals, &[]);
+        let output = redactor.redact(b"usb 1-1: new device number 0, link 1");
+
+        assert_eq!(output, b"usb 1-1: new device number 0, link 1");
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Records the outcome of every collector in the `manifest.json` of the archive.
+
+use super::Collector;
+use anyhow::Context;
+use serde::Serialize;
+use std::ffi::OsStr;
+use std::io;
+use std::path::Path;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+/// Name of the file in the archive which the reports are written to.
+pub const REPORT_FILE: &str = "manifest.json";
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub name: String,
+    pub description: String,
+    /// The command line that was executed, or the path that was copied.
+    pub source: String,
+    pub status: Status,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub bytes: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    Success,
+    Failed,
+    /// The command is not installed, or the file does not exist.
+    NotFound,
+    /// The collector was stopped for taking too long, but its partial output is kept.
+    TimedOut,
+}
+
+impl Report {
+    /// Describes the outcome of a collector whose output was stored at `output`.
+    ///
+    /// Commands provide their exit status, which is considered a failure if unsuccessful,
+    /// and the command lines they were run with, which are recorded as the source.
+    pub fn new(
+        collector: &Collector,
+        executed: &[String],
+        duration: Duration,
+        output: &Path,
+        result: &anyhow::Result<Option<ExitStatus>>,
+    ) -> Self {
+        let (status, exit_code, error) = match result {
+            Ok(Some(exit)) if !exit.success() => (
+                Status::Failed,
+                exit.code(),
+                Some(format!("exited with {}", exit)),
+            ),
+
+            Ok(exit) => (Status::Success, exit.and_then(|exit| exit.code()), None),
+
+            Err(why) => {
+                let status = match why.downcast_ref::<io::Error>().map(io::Error::kind) {
+                    Some(io::ErrorKind::NotFound) => Status::NotFound,
+                    Some(io::ErrorKind::TimedOut) => Status::TimedOut,
+                    _ => Status::Failed,
+                };
+
+                (status, None, Some(format!("{:#}", why)))
+            }
+        };
+
+        Self {
+            name: collector.name.clone(),
+            description: collector.description.clone(),
+            source: if executed.is_empty() {
+                collector.source.to_string()
+            } else {
+                executed.join("; ")
+            },
+            status,
+            exit_code,
+            duration_ms: duration.as_millis() as u64,
+            bytes: bytes_in(output),
+            error,
+        }
+    }
+}
+
+/// Formats a command and its arguments as they would be typed in a shell.
+pub fn command_line<A: AsRef<OsStr>>(command: &str, args: &[A]) -> String {
+    let mut line = command.to_owned();
+
+    for arg in args {
+        let arg = arg.as_ref().to_string_lossy();
+        let is_plain = !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_=@:/.,+%".contains(c));
+
+        line.push(' ');
+
+        if is_plain {
+            line.push_str(&arg);
+        } else {
+            line.push('\'');
+            line.push_str(&arg.replace('\'', "'\\''"));
+            line.push('\'');
+        }
+    }
+
+    line
+}
+
+/// Serializes the reports as JSON, to be stored as [`REPORT_FILE`].
+pub fn serialize(reports: &[Report]) -> anyhow::Result<Vec<u8>> {
+    serde_json::to_vec_pretty(reports).context("failed to serialize reports")
+}
+
+/// Size of a file, or of every file within a directory.
+fn bytes_in(path: &Path) -> u64 {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return 0,
+    };
+
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| bytes_in(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
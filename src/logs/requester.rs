@@ -0,0 +1,215 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Identifies the user who requested logs through pkexec, so that the archive
+//! is only written where they could have written it, and is given to them.
+//!
+//! The destination requested on the command line is not trusted: it must be
+//! the home directory of the requester, or one of their XDG user directories.
+
+use anyhow::Context;
+use nix::fcntl::{openat, OFlag};
+use nix::sys::stat::{fchmod, Mode};
+use nix::unistd::{fchown, getgrouplist, Gid, Uid, User};
+use std::ffi::CString;
+use std::fs::{File, Metadata, OpenOptions};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Component, Path, PathBuf};
+
+const WRITE: u32 = 0o2;
+const SEARCH: u32 = 0o1;
+
+#[derive(Debug)]
+pub struct Requester {
+    pub uid: Uid,
+    pub gid: Gid,
+    pub name: String,
+    pub home: PathBuf,
+    pub groups: Vec<Gid>,
+}
+
+impl Requester {
+    /// The user identified by `PKEXEC_UID`, if the process was started by pkexec.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let uid = match std::env::var("PKEXEC_UID") {
+            Ok(uid) => uid
+                .parse::<u32>()
+                .context("PKEXEC_UID is not a valid UID")?,
+            Err(_) => return Ok(None),
+        };
+
+        let user = User::from_uid(Uid::from_raw(uid))
+            .context("failed to look up the user who requested logs")?
+            .with_context(|| format!("there is no user with UID {}", uid))?;
+
+        let name = CString::new(user.name.as_str()).context("user name contains a null byte")?;
+        let groups = getgrouplist(&name, user.gid).context("failed to get groups of user")?;
+
+        Ok(Some(Self {
+            uid: user.uid,
+            gid: user.gid,
+            name: user.name,
+            home: user.dir,
+            groups,
+        }))
+    }
+
+    /// Whether the user may create files in the directory.
+    pub fn can_write(&self, dir: &Path) -> bool {
+        let can_search = dir.ancestors().skip(1).all(|ancestor| {
+            matches!(std::fs::metadata(ancestor), Ok(meta) if self.permits(&meta, SEARCH))
+        });
+
+        can_search
+            && matches!(
+                std::fs::metadata(dir),
+                Ok(meta) if meta.is_dir() && self.permits(&meta, WRITE | SEARCH)
+            )
+    }
+
+    /// Fails if the user may not create files in the directory.
+    pub fn check_writable(&self, dir: &Path) -> anyhow::Result<()> {
+        if !self.can_write(dir) {
+            anyhow::bail!("{} is not writable by {}", dir.display(), self.name);
+        }
+
+        Ok(())
+    }
+
+    /// Fails unless the directory is the home directory of the user, or one of
+    /// their XDG user directories, which they own and may write to.
+    ///
+    /// Returns the canonical path of the directory.
+    pub fn check_destination(&self, dir: &Path) -> anyhow::Result<PathBuf> {
+        let metadata = std::fs::symlink_metadata(dir)
+            .with_context(|| format!("cannot access {}", dir.display()))?;
+
+        if metadata.file_type().is_symlink() {
+            anyhow::bail!("{} is a symbolic link", dir.display());
+        }
+
+        let canonical = dir
+            .canonicalize()
+            .with_context(|| format!("cannot resolve {}", dir.display()))?;
+
+        let allowed = self
+            .allowed_dirs()
+            .iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .any(|dir| dir == canonical);
+
+        if !allowed {
+            anyhow::bail!(
+                "{} is neither the home directory of {} nor one of their user directories",
+                dir.display(),
+                self.name
+            );
+        }
+
+        if metadata.uid() != self.uid.as_raw() {
+            anyhow::bail!("{} is not owned by {}", dir.display(), self.name);
+        }
+
+        self.check_writable(&canonical)?;
+
+        Ok(canonical)
+    }
+
+    /// The home directory of the user, and the XDG user directories inside of it.
+    fn allowed_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = vec![self.home.clone()];
+
+        let config = self.home.join(".config/user-dirs.dirs");
+
+        if let Ok(config) = std::fs::read_to_string(&config) {
+            for line in config.lines() {
+                let value = match line.split_once('=') {
+                    Some((key, value)) if key.starts_with("XDG_") && key.ends_with("_DIR") => {
+                        value.trim().trim_matches('"')
+                    }
+                    _ => continue,
+                };
+
+                if let Some(relative) = value.strip_prefix("$HOME/") {
+                    let dir = self.home.join(relative);
+
+                    // Prevents `..` from escaping the home directory.
+                    if dir.components().all(|c| c != Component::ParentDir) {
+                        dirs.push(dir);
+                    }
+                }
+            }
+        }
+
+        dirs
+    }
+
+    /// Checks the permission bits that apply to this user against the requested access.
+    fn permits(&self, metadata: &Metadata, access: u32) -> bool {
+        let mode = metadata.mode();
+
+        let bits = if metadata.uid() == self.uid.as_raw() {
+            mode >> 6
+        } else if self.groups.contains(&Gid::from_raw(metadata.gid())) {
+            mode >> 3
+        } else {
+            mode
+        };
+
+        bits & access == access
+    }
+}
+
+/// Creates the file that the archive will be written to, readable only by its owner.
+///
+/// When logs were requested through pkexec, the file is owned by the requester,
+/// and is only created in a directory that passes [`Requester::check_destination`].
+///
+/// The file must not already exist, and symbolic links are never followed.
+pub fn create(dir: &Path, name: &str, requester: Option<&Requester>) -> anyhow::Result<File> {
+    let dir = match requester {
+        Some(requester) => requester.check_destination(dir)?,
+        None => dir.to_owned(),
+    };
+
+    // Everything from here is relative to this handle, so the directory cannot
+    // be swapped for a symbolic link after it has been checked.
+    let dir_handle = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECTORY | libc::O_NOFOLLOW)
+        .open(&dir)
+        .with_context(|| format!("failed to open {}", dir.display()))?;
+
+    if let Some(requester) = requester {
+        let metadata = dir_handle
+            .metadata()
+            .context("failed to get metadata of destination")?;
+
+        if metadata.uid() != requester.uid.as_raw() {
+            anyhow::bail!("{} is not owned by {}", dir.display(), requester.name);
+        }
+    }
+
+    let fd = openat(
+        dir_handle.as_raw_fd(),
+        name,
+        OFlag::O_WRONLY | OFlag::O_CREAT | OFlag::O_EXCL | OFlag::O_NOFOLLOW | OFlag::O_CLOEXEC,
+        Mode::from_bits_truncate(0o600),
+    )
+    .with_context(|| format!("failed to create {}", dir.join(name).display()))?;
+
+    // Safety: the descriptor was just opened, and nothing else owns it.
+    let file = unsafe { File::from_raw_fd(fd) };
+
+    // The process umask may have restricted the mode given above.
+    fchmod(file.as_raw_fd(), Mode::from_bits_truncate(0o600))
+        .context("failed to set permissions of archive")?;
+
+    if let Some(requester) = requester {
+        fchown(file.as_raw_fd(), Some(requester.uid), Some(requester.gid))
+            .context("failed to give archive to the user who requested it")?;
+    }
+
+    Ok(file)
+}
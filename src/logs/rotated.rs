@@ -0,0 +1,118 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Finds the rotations of a log file, such as `syslog.1` and `syslog.2.gz`,
+//! and optionally joins them into one file that can be searched at once.
+
+use super::copy_until;
+use anyhow::Context;
+use flate2::read::MultiGzDecoder;
+use std::cmp::Reverse;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+/// A log file, or one of its rotations.
+pub struct Rotation {
+    pub path: PathBuf,
+    pub file_name: String,
+
+    /// Zero for the current log, and increasing with age for its rotations.
+    pub index: u32,
+}
+
+impl Rotation {
+    fn is_compressed(&self) -> bool {
+        self.path.extension() == Some(OsStr::new("gz"))
+    }
+}
+
+/// The log and those of its rotations which were modified since the given
+/// time, from oldest to newest. The current log is always included.
+pub fn rotations(path: &Path, since: Option<SystemTime>) -> anyhow::Result<Vec<Rotation>> {
+    std::fs::metadata(path).context("failed to read log")?;
+
+    let dir = path.parent().context("log has no parent directory")?;
+    let base = path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .context("log file name is not UTF-8")?;
+
+    let mut rotations = Vec::new();
+
+    for entry in std::fs::read_dir(dir)
+        .context("failed to read log directory")?
+        .filter_map(Result::ok)
+    {
+        let file_name = match entry.file_name().into_string() {
+            Ok(file_name) => file_name,
+            Err(_) => continue,
+        };
+
+        let index = match rotation_index(base, &file_name) {
+            Some(index) => index,
+            None => continue,
+        };
+
+        // A rotation is last modified when it stops being written to, so
+        // nothing within it is newer than this.
+        if let (Some(since), Ok(modified)) = (since, entry.metadata().and_then(|m| m.modified())) {
+            if modified < since && index != 0 {
+                continue;
+            }
+        }
+
+        rotations.push(Rotation {
+            path: entry.path(),
+            file_name,
+            index,
+        });
+    }
+
+    rotations.sort_by_key(|rotation| Reverse(rotation.index));
+
+    Ok(rotations)
+}
+
+/// Writes the rotations to one file in the order given, decompressing them.
+///
+/// Stops writing once the deadline has passed, keeping what was written so far.
+pub fn concatenate(
+    rotations: &[Rotation],
+    mut dest: File,
+    deadline: Instant,
+) -> anyhow::Result<()> {
+    for rotation in rotations {
+        let file = File::open(&rotation.path)
+            .with_context(|| format!("failed to open {}", rotation.path.display()))?;
+
+        let mut reader: Box<dyn Read> = if rotation.is_compressed() {
+            Box::new(MultiGzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+
+        copy_until(&mut reader, &mut dest, deadline)
+            .with_context(|| format!("failed to concatenate {}", rotation.file_name))?;
+    }
+
+    dest.flush().context("failed to write concatenated log")
+}
+
+/// The position of a file among the rotations of the base log, if it is one.
+fn rotation_index(base: &str, file_name: &str) -> Option<u32> {
+    if file_name == base {
+        return Some(0);
+    }
+
+    let suffix = file_name.strip_prefix(base)?.strip_prefix('.')?;
+    let number = suffix.strip_suffix(".gz").unwrap_or(suffix);
+
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    number.parse().ok()
+}
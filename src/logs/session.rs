@@ -0,0 +1,122 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Collects data from the session of the user, which the privileged helper
+//! cannot access, and merges it into the archive written by the helper.
+//!
+//! The unprivileged process runs every collector of the [`Phase::User`] phase
+//! into a directory, which is passed to the helper with `--user-data`. The
+//! helper then copies that directory into [`USER_DIR`] of the archive.
+
+use super::{report, run, Manifest, Phase, TimeWindow, REPORT_FILE};
+use anyhow::Context;
+use nix::dir::Dir;
+use nix::fcntl::{openat, OFlag};
+use nix::sys::stat::{fstat, FileStat, Mode, SFlag};
+use nix::unistd::Uid;
+use std::ffi::CStr;
+use std::fs::File;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::path::Path;
+
+/// Where data from the session of the user is stored in the archive.
+pub const USER_DIR: &str = "user";
+
+/// Runs the collectors of the user phase, storing their output and reports in `dir`.
+pub async fn collect_session(dir: &Path, window: &TimeWindow) -> anyhow::Result<()> {
+    let manifest = Manifest::load();
+    let reports = run(
+        manifest.collectors(Phase::User),
+        dir,
+        window,
+        Uid::current(),
+    )
+    .await;
+
+    let reports = report::serialize(&reports)?;
+
+    std::fs::write(dir.join(REPORT_FILE), reports).context("failed to write reports")
+}
+
+/// Copies data collected by an unprivileged process from `source` into `dest`.
+///
+/// The source was written by the user, so when they are known, only regular
+/// files and directories owned by them are copied. Symbolic links are never
+/// followed, and every file is opened relative to its checked parent.
+pub fn import(source: &Path, dest: &Path, owner: Option<Uid>) -> anyhow::Result<()> {
+    let dir = Dir::open(source, OFlag::O_DIRECTORY | open_flags(), Mode::empty())
+        .with_context(|| format!("failed to open {}", source.display()))?;
+
+    check_owner(dir.as_raw_fd(), owner)?;
+
+    import_dir(dir, dest, owner)
+}
+
+fn import_dir(mut dir: Dir, dest: &Path, owner: Option<Uid>) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dest)
+        .with_context(|| format!("failed to create {}", dest.display()))?;
+
+    let names = dir
+        .iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_owned())
+        .filter(|name| name.as_bytes() != b"." && name.as_bytes() != b"..")
+        .collect::<Vec<_>>();
+
+    for name in names {
+        let dest = match name.to_str() {
+            Ok(file_name) => dest.join(file_name),
+            Err(_) => continue,
+        };
+
+        if let Err(why) = import_entry(dir.as_raw_fd(), &name, &dest, owner) {
+            eprintln!("skipping user data {}: {:?}", dest.display(), why);
+        }
+    }
+
+    Ok(())
+}
+
+fn import_entry(dir: RawFd, name: &CStr, dest: &Path, owner: Option<Uid>) -> anyhow::Result<()> {
+    let fd = openat(dir, name, open_flags(), Mode::empty()).context("failed to open")?;
+
+    // Safety: the descriptor was just opened, and nothing else owns it.
+    let file = unsafe { File::from_raw_fd(fd) };
+
+    let kind = check_owner(file.as_raw_fd(), owner)?.st_mode & SFlag::S_IFMT.bits();
+
+    if kind == SFlag::S_IFDIR.bits() {
+        let dir = Dir::from_fd(file.into_raw_fd()).context("failed to read directory")?;
+        import_dir(dir, dest, owner)
+    } else if kind == SFlag::S_IFREG.bits() {
+        copy_file(file, dest)
+    } else {
+        anyhow::bail!("not a regular file or directory");
+    }
+}
+
+fn copy_file(mut file: File, dest: &Path) -> anyhow::Result<()> {
+    let mut output = File::create(dest).context("failed to create file")?;
+
+    std::io::copy(&mut file, &mut output)
+        .map(|_| ())
+        .context("failed to copy file")
+}
+
+/// Fails unless the file is owned by the expected user.
+fn check_owner(fd: RawFd, owner: Option<Uid>) -> anyhow::Result<FileStat> {
+    let stat = fstat(fd).context("failed to get metadata")?;
+
+    if let Some(owner) = owner {
+        if stat.st_uid != owner.as_raw() {
+            anyhow::bail!("not owned by the user who requested logs");
+        }
+    }
+
+    Ok(stat)
+}
+
+/// Opens without following symbolic links, and without blocking on a FIFO.
+fn open_flags() -> OFlag {
+    OFlag::O_RDONLY | OFlag::O_NOFOLLOW | OFlag::O_NONBLOCK | OFlag::O_CLOEXEC
+}
@@ -0,0 +1,207 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! The span of time that journals and log files are collected from.
+
+use super::collector::DEFAULT_TIMEOUT;
+use super::timeout;
+use anyhow::Context;
+use futures::io::AsyncBufReadExt;
+use smol::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimeWindow {
+    /// Everything logged since the given time.
+    Since(SystemTime),
+    /// The given number of most recent boots, including the current boot.
+    Boots(u32),
+    /// Specific boots, identified by their 128-bit boot IDs.
+    BootIds(Vec<String>),
+}
+
+impl Default for TimeWindow {
+    fn default() -> Self {
+        TimeWindow::days_ago(1)
+    }
+}
+
+impl TimeWindow {
+    /// Everything since midnight, local time, the given number of days ago.
+    pub fn days_ago(days: u32) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs()) as libc::time_t;
+
+        // Safety: `tm` is fully initialized by `localtime_r` before use.
+        let mut tm = unsafe {
+            let mut tm = std::mem::zeroed::<libc::tm>();
+            libc::localtime_r(&now, &mut tm);
+            tm
+        };
+
+        tm.tm_mday -= days as i32;
+        tm.tm_hour = 0;
+        tm.tm_min = 0;
+        tm.tm_sec = 0;
+
+        TimeWindow::Since(local_time(tm))
+    }
+
+    /// Arguments for `pop-support generate-logs` which select this window.
+    pub fn to_args(&self) -> Vec<String> {
+        match self {
+            TimeWindow::Since(time) => vec!["--since".into(), format!("@{}", unix_secs(*time))],
+            TimeWindow::Boots(boots) => vec!["--boots".into(), boots.to_string()],
+            TimeWindow::BootIds(ids) => ids
+                .iter()
+                .flat_map(|id| vec!["--boot".to_owned(), id.clone()])
+                .collect(),
+        }
+    }
+
+    /// Arguments for `journalctl` that select each part of this window.
+    ///
+    /// Boots are collected separately, so each set of arguments is paired with a
+    /// name that distinguishes its output from the others.
+    pub fn journal_args(&self) -> Vec<(Option<String>, Vec<String>)> {
+        match self {
+            TimeWindow::Since(time) => {
+                vec![(None, vec![format!("--since=@{}", unix_secs(*time))])]
+            }
+
+            TimeWindow::Boots(boots) => (0..*boots)
+                .map(|offset| {
+                    let name = format!("boot-{}", offset);
+                    (Some(name), vec![format!("--boot=-{}", offset)])
+                })
+                .collect(),
+
+            TimeWindow::BootIds(ids) => ids
+                .iter()
+                .map(|id| (Some(id.clone()), vec![format!("--boot={}", id)]))
+                .collect(),
+        }
+    }
+
+    /// The earliest time within the window, if it can be determined.
+    pub async fn start(&self) -> Option<SystemTime> {
+        match self {
+            TimeWindow::Since(time) => Some(*time),
+
+            TimeWindow::Boots(boots) => boot_start(&format!("-{}", boots.saturating_sub(1))).await,
+
+            TimeWindow::BootIds(ids) => {
+                let starts = futures::future::join_all(ids.iter().map(|id| boot_start(id))).await;
+                starts.into_iter().flatten().min()
+            }
+        }
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = anyhow::Error;
+
+    /// Parses the value of `--since`, which may be `today`, `yesterday`, a date
+    /// such as `2022-05-16`, a date and time such as `2022-05-16 13:30:00`, or
+    /// seconds since the Unix epoch prefixed with `@`.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "today" => return Ok(TimeWindow::days_ago(0)),
+            "yesterday" => return Ok(TimeWindow::days_ago(1)),
+            _ => (),
+        }
+
+        if let Some(secs) = input.strip_prefix('@') {
+            let secs = secs.parse::<u64>().context("invalid Unix timestamp")?;
+            return Ok(TimeWindow::Since(UNIX_EPOCH + Duration::from_secs(secs)));
+        }
+
+        let (date, time) = input.split_once(' ').unwrap_or((input, "00:00:00"));
+
+        let date = date
+            .split('-')
+            .map(str::parse::<i32>)
+            .collect::<Result<Vec<_>, _>>()
+            .context("invalid date")?;
+
+        let mut time = time
+            .split(':')
+            .map(str::parse::<i32>)
+            .collect::<Result<Vec<_>, _>>()
+            .context("invalid time")?;
+
+        time.resize(3, 0);
+
+        if date.len() != 3 || time.len() != 3 {
+            anyhow::bail!("expected a date formatted as YYYY-MM-DD [HH:MM[:SS]]");
+        }
+
+        // Safety: an all-zero `tm` is valid, and every field used is set below.
+        let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+        tm.tm_year = date[0] - 1900;
+        tm.tm_mon = date[1] - 1;
+        tm.tm_mday = date[2];
+        tm.tm_hour = time[0];
+        tm.tm_min = time[1];
+        tm.tm_sec = time[2];
+
+        Ok(TimeWindow::Since(local_time(tm)))
+    }
+}
+
+/// Validates a boot ID before it is given to `journalctl`.
+pub fn parse_boot_id(input: &str) -> anyhow::Result<String> {
+    if input.len() == 32 && input.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(input.to_ascii_lowercase())
+    } else {
+        Err(anyhow::anyhow!("boot ID must be 32 hexadecimal characters"))
+    }
+}
+
+/// Converts a broken-down local time into a point in time.
+fn local_time(mut tm: libc::tm) -> SystemTime {
+    // Let the C library determine whether daylight saving time applies.
+    tm.tm_isdst = -1;
+
+    // Safety: `mktime` only reads and normalizes the given struct.
+    let secs = unsafe { libc::mktime(&mut tm) };
+
+    UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
+
+/// Seconds since the Unix epoch, or zero for times before it.
+pub(super) fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+/// The time of the first journal entry of a boot.
+///
+/// Collectors wait for this, so it gives up after the default timeout of a collector.
+async fn boot_start(boot: &str) -> Option<SystemTime> {
+    let first_entry = async move { first_entry(boot).await.context("no journal entries") };
+
+    timeout(DEFAULT_TIMEOUT, first_entry).await.ok()
+}
+
+async fn first_entry(boot: &str) -> Option<SystemTime> {
+    let mut child = Command::new("journalctl")
+        .arg(format!("--boot={}", boot))
+        .args(["--output=short-unix", "--quiet", "--no-pager"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .ok()?;
+
+    let mut line = String::new();
+    let mut stdout = futures::io::BufReader::new(child.stdout.take()?);
+    stdout.read_line(&mut line).await.ok()?;
+
+    let secs = line.split_whitespace().next()?.parse::<f64>().ok()?;
+
+    Some(UNIX_EPOCH + Duration::from_secs(secs as u64))
+}
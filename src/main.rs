@@ -3,7 +3,10 @@
 
 use clap::Parser;
 use gtk::prelude::*;
+use pop_support::logs::{parse_boot_id, Format, TimeWindow};
+use pop_support::support_info::{InfoFormat, SupportInfo};
 use pop_support::SupportPanel;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -15,11 +18,60 @@ struct Args {
 enum Action {
     GenerateLogs(LogAction),
     Gtk,
+    /// Print the model, operating system, kernel, and hardware of this computer
+    Info(InfoAction),
+    /// Print the serial number of the product, which only root may read
+    Serial,
 }
 
 #[derive(Debug, Parser)]
 pub struct LogAction {
     pub path: String,
+
+    /// Keep serial numbers, addresses, and other identifying information in the logs
+    #[clap(long)]
+    pub no_redact: bool,
+
+    /// Compression of the archive: xz, zstd, gzip, or zip
+    #[clap(long, default_value = "xz")]
+    pub format: Format,
+
+    /// Collect logs since a time: today, yesterday, YYYY-MM-DD [HH:MM[:SS]], or @SECONDS
+    #[clap(long, conflicts_with_all = &["boots", "boot"])]
+    pub since: Option<TimeWindow>,
+
+    /// Collect logs from this many of the most recent boots
+    #[clap(long, conflicts_with = "boot")]
+    pub boots: Option<u32>,
+
+    /// Collect logs from the boot with this ID, which may be given more than once
+    #[clap(long, multiple_occurrences = true, parse(try_from_str = parse_boot_id))]
+    pub boot: Vec<String>,
+
+    /// Include data collected from the session of the user in this directory
+    #[clap(long)]
+    pub user_data: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+pub struct InfoAction {
+    /// Output format: text, json, or kv
+    #[clap(long, default_value = "text")]
+    pub format: InfoFormat,
+}
+
+impl LogAction {
+    fn window(&self) -> TimeWindow {
+        if let Some(since) = &self.since {
+            since.clone()
+        } else if let Some(boots) = self.boots {
+            TimeWindow::Boots(boots.max(1))
+        } else if !self.boot.is_empty() {
+            TimeWindow::BootIds(self.boot.clone())
+        } else {
+            TimeWindow::default()
+        }
+    }
 }
 
 fn main() {
@@ -27,8 +79,10 @@ fn main() {
         let args = Args::parse();
 
         if let Err(why) = match args.action {
-            Action::GenerateLogs(action) => generate_logs(&action.path).await,
+            Action::GenerateLogs(action) => generate_logs(action).await,
             Action::Gtk => gtk(),
+            Action::Info(action) => info(action).await,
+            Action::Serial => serial(),
         } {
             eprintln!("{:?}", why);
             std::process::exit(1);
@@ -36,13 +90,42 @@ fn main() {
     })
 }
 
-async fn generate_logs(path: &str) -> anyhow::Result<()> {
+async fn generate_logs(action: LogAction) -> anyhow::Result<()> {
     use pop_support::logs;
 
-    let path = logs::generate(path).await?;
+    let options = logs::Options {
+        redact: !action.no_redact,
+        format: action.format,
+        requester: logs::Requester::from_env()?,
+        window: action.window(),
+        user_data: action.user_data.clone(),
+    };
+
+    let archive = logs::generate(&action.path, &options).await?;
+
+    println!("PATH {}", archive.path);
 
-    println!("PATH {path}");
+    for recipient in &archive.encrypted_for {
+        println!("ENCRYPTED_FOR {recipient}");
+    }
+
+    Ok(())
+}
+
+async fn info(action: InfoAction) -> anyhow::Result<()> {
+    let info = SupportInfo::fetch().await;
+    print!("{}", info.format(action.format)?);
+
+    // Text and key-value output already end with a newline.
+    if action.format == InfoFormat::Json {
+        println!();
+    }
+
+    Ok(())
+}
 
+fn serial() -> anyhow::Result<()> {
+    println!("{}", pop_support::support_info::read_serial()?);
     Ok(())
 }
 
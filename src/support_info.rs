@@ -1,7 +1,16 @@
+mod hardware;
+
+pub use self::hardware::*;
+
 use crate::vendor::Vendor;
+use anyhow::Context;
 use concat_in_place::strcat;
+use nix::sys::utsname::uname;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use smol::fs::read_to_string;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use const_format::concatcp;
 
@@ -9,10 +18,40 @@ const DMI_DIR: &str = "/sys/devices/virtual/dmi/id/";
 const BOARD_NAME: &str = concatcp!(DMI_DIR, "board_name");
 const BOARD_VERSION: &str = concatcp!(DMI_DIR, "board_version");
 const PRODUCT_NAME: &str = concatcp!(DMI_DIR, "product_name");
+const PRODUCT_SERIAL: &str = concatcp!(DMI_DIR, "product_serial");
 const PRODUCT_VERSION: &str = concatcp!(DMI_DIR, "product_version");
 const SYS_VENDOR: &str = concatcp!(DMI_DIR, "sys_vendor");
 
-#[derive(Debug, Default)]
+const OS_RELEASE: &str = "/etc/os-release";
+const KERNEL_RELEASE: &str = "/proc/sys/kernel/osrelease";
+const KERNEL_VERSION: &str = "/proc/sys/kernel/version";
+
+/// How the support information is printed by `pop-support info`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InfoFormat {
+    /// The same text as `systeminfo.txt` in log archives.
+    Text,
+    Json,
+    /// A `key=value` line for every field, with nested keys joined by dots.
+    KeyValue,
+}
+
+impl FromStr for InfoFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "text" => Ok(InfoFormat::Text),
+            "json" => Ok(InfoFormat::Json),
+            "kv" => Ok(InfoFormat::KeyValue),
+            _ => Err(anyhow::anyhow!(
+                "unsupported info format: expected text, json, or kv"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct SupportInfo {
     pub vendor: Option<Vendor>,
     pub model_and_version: String,
@@ -20,22 +59,45 @@ pub struct SupportInfo {
     pub operating_system: String,
     pub kernel_version: String,
     pub kernel_revision: String,
+    pub kernel_build: String,
+    pub hardware: Hardware,
 }
 
 impl SupportInfo {
     pub async fn fetch() -> Self {
-        let vendor = Vendor::guess();
+        Self::fetch_from(Path::new("/")).await
+    }
+
+    /// Fetches the information of the system whose root filesystem is at `root`.
+    pub async fn fetch_from(root: &Path) -> Self {
+        let vendor = {
+            let root = root.to_owned();
+            smol::unblock(move || Vendor::guess_from(&root)).await
+        };
 
         let (dmi_name, dmi_version) = match vendor {
             Some(_) => (PRODUCT_NAME, PRODUCT_VERSION),
             None => (BOARD_NAME, BOARD_VERSION),
         };
 
-        let (sys_vendor, version, product_name, os_release) = futures::join!(
-            read_to_string(SYS_VENDOR),
-            read_to_string(dmi_version),
-            read_to_string(dmi_name),
-            read_to_string("/etc/os-release"),
+        let hardware = {
+            let root = root.to_owned();
+            smol::unblock(move || Hardware::read_from(&root))
+        };
+
+        // Only readable by root, so this is usually fetched later with `read_serial_subprocess`.
+        let serial_number = {
+            let root = root.to_owned();
+            smol::unblock(move || read_serial_from(&root).unwrap_or_default())
+        };
+
+        let (sys_vendor, version, product_name, os_release, hardware, serial_number) = futures::join!(
+            read_to_string(rooted(root, SYS_VENDOR)),
+            read_to_string(rooted(root, dmi_version)),
+            read_to_string(rooted(root, dmi_name)),
+            read_to_string(rooted(root, OS_RELEASE)),
+            hardware,
+            serial_number,
         );
 
         let mut model_and_version = String::new();
@@ -92,28 +154,27 @@ impl SupportInfo {
             }
         }
 
-        let uname_r = Command::new("uname")
-            .arg("-r")
-            .output()
-            .expect("failed to get kernel release");
-        let kernel_version = String::from_utf8_lossy(&uname_r.stdout)
-            .to_string()
-            .trim()
-            .to_string();
+        // uname(2) describes the running kernel, which only belongs to the actual root.
+        let (release, version) = if root == Path::new("/") {
+            let uname = uname();
+            (uname.release().to_owned(), uname.version().to_owned())
+        } else {
+            let (release, version) = futures::join!(
+                read_to_string(rooted(root, KERNEL_RELEASE)),
+                read_to_string(rooted(root, KERNEL_VERSION)),
+            );
 
-        let uname_v = Command::new("uname")
-            .arg("-v")
-            .output()
-            .expect("failed to get kernel version");
+            (release.unwrap_or_default(), version.unwrap_or_default())
+        };
 
-        let kernel_revision = String::from_utf8_lossy(&uname_v.stdout)
-            .to_string()
-            .split_whitespace()
-            .next()
-            .unwrap_or_default()
-            .to_string();
+        // The version begins with the build number, such as `#202208091832~1660161327~22.04~0b5e8d3`,
+        // followed by the configuration and date of the build.
+        let (kernel_revision, kernel_build) = match version.trim().split_once(' ') {
+            Some((revision, build)) => (revision.to_owned(), build.trim().to_owned()),
+            None => (version.trim().to_owned(), String::new()),
+        };
 
-        let serial_number = String::new();
+        let kernel_version = release.trim().to_owned();
 
         Self {
             model_and_version,
@@ -122,6 +183,115 @@ impl SupportInfo {
             vendor,
             kernel_version,
             kernel_revision,
+            kernel_build,
+            hardware,
+        }
+    }
+
+    /// Formats the information in the given format.
+    pub fn format(&self, format: InfoFormat) -> anyhow::Result<String> {
+        match format {
+            InfoFormat::Text => Ok(self.text()),
+            InfoFormat::Json => {
+                serde_json::to_string_pretty(self).context("failed to serialize support info")
+            }
+            InfoFormat::KeyValue => {
+                let value =
+                    serde_json::to_value(self).context("failed to serialize support info")?;
+                let mut output = String::new();
+                key_values(&mut output, "", &value);
+                Ok(output)
+            }
+        }
+    }
+
+    /// Human-readable text, as written to `systeminfo.txt`.
+    pub fn text(&self) -> String {
+        let hardware = &self.hardware;
+
+        let mut output = fomat_macros::fomat! {
+            "Model: " (self.model_and_version) "\n"
+            "OS Version: " (self.operating_system) "\n"
+            "Kernel Version: " (self.kernel_version) "\n"
+            "Kernel Revision: " (self.kernel_revision) "\n"
+            "Kernel Build: " (self.kernel_build) "\n"
+            "Firmware: " (hardware.firmware) "\n"
+        };
+
+        if !self.serial_number.is_empty() {
+            output.push_str(&fomat_macros::fomat!("Serial Number: " (self.serial_number) "\n"));
+        }
+
+        if let Some(cpu) = &hardware.cpu {
+            output.push_str(&fomat_macros::fomat!("CPU: " (cpu) "\n"));
+        }
+
+        if let Some(memory) = hardware.memory {
+            let memory = format_size(memory);
+            output.push_str(&fomat_macros::fomat!("Memory: " (memory) "\n"));
+        }
+
+        for gpu in &hardware.gpus {
+            output.push_str(&fomat_macros::fomat!("GPU: " (gpu.slot) " " (gpu) "\n"));
+        }
+
+        for device in &hardware.storage {
+            output.push_str(&fomat_macros::fomat!("Storage: " (device) "\n"));
+        }
+
+        for battery in &hardware.batteries {
+            output.push_str(&fomat_macros::fomat!("Battery: " (battery) "\n"));
+        }
+
+        output
+    }
+}
+
+/// Writes a `key=value` line for each scalar within the value.
+fn key_values(output: &mut String, key: &str, value: &Value) {
+    let join = |child: &str| {
+        if key.is_empty() {
+            child.to_owned()
+        } else {
+            [key, ".", child].concat()
+        }
+    };
+
+    match value {
+        Value::Object(fields) => {
+            for (name, value) in fields {
+                key_values(output, &join(name), value);
+            }
+        }
+        Value::Array(values) => {
+            for (index, value) in values.iter().enumerate() {
+                key_values(output, &join(&index.to_string()), value);
+            }
+        }
+        Value::Null => {
+            strcat!(output, key "=\n");
+        }
+        Value::String(value) => {
+            strcat!(output, key "=" value "\n");
+        }
+        value => {
+            strcat!(output, key "=" value.to_string().as_str() "\n");
         }
     }
 }
+
+/// Reads the serial number of the product from the DMI table, which requires root.
+pub fn read_serial() -> anyhow::Result<String> {
+    read_serial_from(Path::new("/"))
+}
+
+fn read_serial_from(root: &Path) -> anyhow::Result<String> {
+    std::fs::read_to_string(rooted(root, PRODUCT_SERIAL))
+        .map(|serial| serial.trim().to_owned())
+        .context("failed to read product serial")
+}
+
+/// The location of an absolute path within the filesystem at `root`.
+pub(crate) fn rooted(root: &Path, path: &str) -> PathBuf {
+    root.join(path.trim_start_matches('/'))
+}
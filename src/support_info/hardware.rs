@@ -0,0 +1,414 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! An inventory of the hardware, read from sysfs and `/proc` without
+//! running any commands. Anything which cannot be read is left out.
+
+use super::{rooted, DMI_DIR};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{read_dir, read_link, read_to_string};
+use std::path::Path;
+
+const BLOCK_DIR: &str = "/sys/block";
+const CPUINFO: &str = "/proc/cpuinfo";
+const MEMINFO: &str = "/proc/meminfo";
+const PCI_DIR: &str = "/sys/bus/pci/devices";
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Block devices which do not represent physical storage.
+const VIRTUAL_BLOCK_DEVICES: &[&str] = &["dm-", "loop", "md", "ram", "zram"];
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Hardware {
+    pub cpu: Option<Cpu>,
+    /// Total memory, in bytes.
+    pub memory: Option<u64>,
+    pub gpus: Vec<Gpu>,
+    pub storage: Vec<Storage>,
+    pub firmware: Firmware,
+    pub batteries: Vec<Battery>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Cpu {
+    pub model: String,
+    pub cores: usize,
+    pub threads: usize,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Gpu {
+    /// The PCI address, such as `0000:01:00.0`.
+    pub slot: String,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    /// The kernel driver bound to the device, if any.
+    pub driver: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Storage {
+    /// The kernel name of the device, such as `nvme0n1`.
+    pub name: String,
+    pub model: String,
+    /// Capacity, in bytes.
+    pub size: u64,
+    pub removable: bool,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Firmware {
+    pub bios_version: String,
+    pub bios_date: String,
+    /// Only reported by firmware which fills in the embedded controller release.
+    pub ec_version: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Battery {
+    pub name: String,
+    /// Capacity the battery was designed for, in µWh or µAh depending on `unit`.
+    pub design_capacity: u64,
+    /// Capacity the battery holds when fully charged now.
+    pub full_capacity: u64,
+    pub unit: CapacityUnit,
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CapacityUnit {
+    #[default]
+    WattHours,
+    AmpHours,
+}
+
+impl Hardware {
+    /// Reads the inventory, which may block on sysfs and should not be called from the UI thread.
+    pub fn read() -> Self {
+        Self::read_from(Path::new("/"))
+    }
+
+    /// Reads the inventory of the system whose root filesystem is at `root`.
+    pub fn read_from(root: &Path) -> Self {
+        Self {
+            cpu: cpu(root),
+            memory: memory(root),
+            gpus: gpus(root),
+            storage: storage(root),
+            firmware: firmware(root),
+            batteries: batteries(root),
+        }
+    }
+}
+
+impl Gpu {
+    /// The name of the vendor, for those which are commonly found.
+    pub fn vendor(&self) -> Option<&'static str> {
+        match self.vendor_id {
+            0x1002 => Some("AMD"),
+            0x10de => Some("NVIDIA"),
+            0x8086 => Some("Intel"),
+            _ => None,
+        }
+    }
+}
+
+impl Battery {
+    /// Full capacity as a percentage of the design capacity.
+    pub fn health(&self) -> u64 {
+        if self.design_capacity == 0 {
+            return 0;
+        }
+
+        self.full_capacity * 100 / self.design_capacity
+    }
+}
+
+impl fmt::Display for Cpu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} cores, {} threads)",
+            self.model, self.cores, self.threads
+        )
+    }
+}
+
+impl fmt::Display for Gpu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(vendor) = self.vendor() {
+            write!(f, "{} ", vendor)?;
+        }
+
+        write!(f, "[{:04x}:{:04x}]", self.vendor_id, self.device_id)?;
+
+        if let Some(driver) = &self.driver {
+            write!(f, " ({})", driver)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.name)?;
+
+        if !self.model.is_empty() {
+            write!(f, "{} ", self.model)?;
+        }
+
+        write!(f, "({})", format_size(self.size))?;
+
+        if self.removable {
+            f.write_str(" removable")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Firmware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.bios_version)?;
+
+        if !self.bios_date.is_empty() {
+            write!(f, " ({})", self.bios_date)?;
+        }
+
+        if let Some(ec) = &self.ec_version {
+            write!(f, ", EC {}", ec)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Battery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.unit {
+            CapacityUnit::WattHours => "Wh",
+            CapacityUnit::AmpHours => "Ah",
+        };
+
+        write!(
+            f,
+            "{}: {:.1} of {:.1} {} ({}%)",
+            self.name,
+            self.full_capacity as f64 / 1_000_000.0,
+            self.design_capacity as f64 / 1_000_000.0,
+            unit,
+            self.health()
+        )
+    }
+}
+
+/// Formats a number of bytes with the largest binary unit which keeps it above one.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn cpu(root: &Path) -> Option<Cpu> {
+    let cpuinfo = read_to_string(rooted(root, CPUINFO)).ok()?;
+
+    let mut model = None;
+    let mut threads = 0;
+    let mut cores = HashSet::new();
+    let mut physical_id = "";
+
+    for line in cpuinfo.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+
+        match key {
+            "processor" => threads += 1,
+            "model name" if model.is_none() => model = Some(value.to_owned()),
+            "physical id" => physical_id = value,
+            "core id" => {
+                cores.insert((physical_id, value));
+            }
+            _ => (),
+        }
+    }
+
+    Some(Cpu {
+        model: model?,
+        // Without topology, as on some ARM systems, each thread is assumed to be a core.
+        cores: if cores.is_empty() {
+            threads
+        } else {
+            cores.len()
+        },
+        threads,
+    })
+}
+
+fn memory(root: &Path) -> Option<u64> {
+    read_to_string(rooted(root, MEMINFO))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))
+        .and_then(|value| value.trim().strip_suffix("kB"))
+        .and_then(|kib| kib.trim().parse::<u64>().ok())
+        .map(|kib| kib * 1024)
+}
+
+fn gpus(root: &Path) -> Vec<Gpu> {
+    let mut gpus = Vec::new();
+
+    let entries = match read_dir(rooted(root, PCI_DIR)) {
+        Ok(entries) => entries,
+        Err(_) => return gpus,
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+
+        // Display controllers have a base class of 0x03.
+        match read_trimmed(&path.join("class")) {
+            Some(class) if class.starts_with("0x03") => (),
+            _ => continue,
+        }
+
+        let (vendor_id, device_id) = match (
+            read_hex(&path.join("vendor")),
+            read_hex(&path.join("device")),
+        ) {
+            (Some(vendor), Some(device)) => (vendor, device),
+            _ => continue,
+        };
+
+        let driver = read_link(path.join("driver"))
+            .ok()
+            .and_then(|driver| Some(driver.file_name()?.to_str()?.to_owned()));
+
+        gpus.push(Gpu {
+            slot: entry.file_name().to_string_lossy().into_owned(),
+            vendor_id,
+            device_id,
+            driver,
+        });
+    }
+
+    gpus.sort_by(|a, b| a.slot.cmp(&b.slot));
+    gpus
+}
+
+fn storage(root: &Path) -> Vec<Storage> {
+    let mut devices = Vec::new();
+
+    let entries = match read_dir(rooted(root, BLOCK_DIR)) {
+        Ok(entries) => entries,
+        Err(_) => return devices,
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if VIRTUAL_BLOCK_DEVICES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        {
+            continue;
+        }
+
+        let path = entry.path();
+
+        // Sizes are always given in 512-byte sectors, whatever the block size.
+        let size = match read_trimmed(&path.join("size")).and_then(|s| s.parse::<u64>().ok()) {
+            Some(sectors) if sectors > 0 => sectors * 512,
+            _ => continue,
+        };
+
+        devices.push(Storage {
+            model: read_trimmed(&path.join("device/model")).unwrap_or_default(),
+            removable: read_trimmed(&path.join("removable")).as_deref() == Some("1"),
+            name,
+            size,
+        });
+    }
+
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    devices
+}
+
+fn firmware(root: &Path) -> Firmware {
+    let dmi = rooted(root, DMI_DIR);
+
+    Firmware {
+        bios_version: read_trimmed(&dmi.join("bios_version")).unwrap_or_default(),
+        bios_date: read_trimmed(&dmi.join("bios_date")).unwrap_or_default(),
+        ec_version: read_trimmed(&dmi.join("ec_firmware_release")).filter(|ec| !ec.is_empty()),
+    }
+}
+
+fn batteries(root: &Path) -> Vec<Battery> {
+    let mut batteries = Vec::new();
+
+    let entries = match read_dir(rooted(root, POWER_SUPPLY_DIR)) {
+        Ok(entries) => entries,
+        Err(_) => return batteries,
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+
+        if read_trimmed(&path.join("type")).as_deref() != Some("Battery") {
+            continue;
+        }
+
+        // Batteries report either energy or charge, depending on the firmware.
+        let capacity = |prefix: &str| {
+            let design = read_trimmed(&path.join([prefix, "_full_design"].concat()))?;
+            let full = read_trimmed(&path.join([prefix, "_full"].concat()))?;
+            Some((design.parse::<u64>().ok()?, full.parse::<u64>().ok()?))
+        };
+
+        let ((design_capacity, full_capacity), unit) = match capacity("energy") {
+            Some(capacity) => (capacity, CapacityUnit::WattHours),
+            None => match capacity("charge") {
+                Some(capacity) => (capacity, CapacityUnit::AmpHours),
+                None => continue,
+            },
+        };
+
+        batteries.push(Battery {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            design_capacity,
+            full_capacity,
+            unit,
+        });
+    }
+
+    batteries.sort_by(|a, b| a.name.cmp(&b.name));
+    batteries
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_owned())
+}
+
+fn read_hex(path: &Path) -> Option<u16> {
+    let value = read_trimmed(path)?;
+    u16::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
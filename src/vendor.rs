@@ -1,9 +1,13 @@
 // Copyright 2022 System76 <info@system76.com>
 // SPDX-License-Identifier: MPL-2.0
 
+use crate::support_info::rooted;
+use serde::{Deserialize, Serialize};
 use std::fs::read_to_string;
+use std::path::Path;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Vendor {
     Hp,
     System76,
@@ -11,11 +15,19 @@ pub enum Vendor {
 
 impl Vendor {
     pub fn guess() -> Option<Self> {
-        if let Ok(sys_vendor) = read_to_string("/sys/devices/virtual/dmi/id/sys_vendor") {
+        Self::guess_from(Path::new("/"))
+    }
+
+    /// Guesses the vendor of the system whose root filesystem is at `root`.
+    pub fn guess_from(root: &Path) -> Option<Self> {
+        let dmi_dir = rooted(root, "/sys/devices/virtual/dmi/id");
+        let dmi = |name: &str| read_to_string(dmi_dir.join(name));
+
+        if let Ok(sys_vendor) = dmi("sys_vendor") {
             #[allow(clippy::single_match)]
             match sys_vendor.trim() {
                 "HP" => {
-                    if let Ok(version) = read_to_string("/sys/devices/virtual/dmi/id/board_name") {
+                    if let Ok(version) = dmi("board_name") {
                         match version.trim() {
                             "8A78" => return Some(Vendor::Hp),
                             _ => (),
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use crate::fl;
+use crate::logs::LogArchive;
 use gtk::prelude::*;
 use relm::Relm;
 
@@ -14,7 +15,7 @@ pub struct LogModel {
 #[derive(relm_derive::Msg)]
 pub enum LogEvent {
     Close,
-    GeneratedLogs(anyhow::Result<String>),
+    GeneratedLogs(anyhow::Result<LogArchive>),
     ShowInFolder,
 }
 
@@ -38,12 +39,20 @@ impl relm::Widget for LogDialog {
                 self.widgets.spinner.hide();
 
                 if let Ok(archive) = result {
-                    let message = fl!("log-dialog-finished", archive = archive.clone());
+                    let message = if archive.encrypted_for.is_empty() {
+                        fl!("log-dialog-finished", archive = archive.path.clone())
+                    } else {
+                        fl!(
+                            "log-dialog-finished-encrypted",
+                            archive = archive.path.clone(),
+                            recipients = archive.encrypted_for.join(", ")
+                        )
+                    };
 
                     self.widgets.description.set_text(&message);
                     self.widgets.show_folder_button.set_sensitive(true);
                     self.widgets.close_button.set_sensitive(true);
-                    self.model.folder = Some(archive);
+                    self.model.folder = Some(archive.path);
 
                     return;
                 }
@@ -0,0 +1,84 @@
+// Copyright 2022 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Compares the information read from the DMI dumps and sysfs trees in
+//! `tests/fixtures` to the expected output beside each of them.
+//!
+//! Set `UPDATE_GOLDEN=1` to rewrite the expected output after an intended change.
+
+use pop_support::support_info::{InfoFormat, SupportInfo};
+use pop_support::Vendor;
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+fn golden(name: &str, vendor: Option<Vendor>) {
+    let root = fixture(name);
+
+    assert_eq!(Vendor::guess_from(&root.join("root")), vendor);
+
+    let info = smol::block_on(SupportInfo::fetch_from(&root.join("root")));
+
+    for (file, format) in [
+        ("expected.txt", InfoFormat::Text),
+        ("expected.json", InfoFormat::Json),
+    ] {
+        let actual = info.format(format).unwrap();
+        let path = root.join(file);
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, &actual).unwrap();
+            continue;
+        }
+
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(actual, expected, "{} differs from the output", path.display());
+    }
+}
+
+#[test]
+fn system76_lemur_pro() {
+    golden("system76-lemp11", Some(Vendor::System76));
+}
+
+#[test]
+fn system76_oryx_pro() {
+    golden("system76-oryp7", Some(Vendor::System76));
+}
+
+#[test]
+fn hp_dev_one() {
+    golden("hp-dev-one", Some(Vendor::Hp));
+}
+
+#[test]
+fn generic_board() {
+    golden("generic-board", None);
+}
+
+#[test]
+fn json_round_trip() {
+    let info = smol::block_on(SupportInfo::fetch_from(&fixture("system76-lemp11/root")));
+    let json = info.format(InfoFormat::Json).unwrap();
+    let parsed: SupportInfo = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(parsed.format(InfoFormat::Json).unwrap(), json);
+}
+
+#[test]
+fn missing_root() {
+    let root = fixture("does-not-exist");
+
+    assert_eq!(Vendor::guess_from(&root), None);
+
+    let info = smol::block_on(SupportInfo::fetch_from(&root));
+
+    assert!(info.model_and_version.is_empty());
+    assert!(info.kernel_version.is_empty());
+    assert!(info.hardware.cpu.is_none());
+    assert!(info.hardware.gpus.is_empty());
+}